@@ -0,0 +1,150 @@
+//! 音频处理热路径的基准测试
+//!
+//! 用合成信号模拟声卡回调，让音频经过与实时识别相同的处理循环 (`ChunkProcessor`:
+//! 重采样、降噪、增益、VAD、切分 chunk)，测量各种采样率/降噪组合下的处理速度和内存分配次数。vosk 识别本身不在测量范围内。
+//! 运行: `cargo bench --bench pipeline`
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use hellcall::core::audio::{AudioRecognizerConfig, ChunkProcessor};
+use hellcall::core::event::EventEmitter;
+use hellcall::core::ring::sample_ring;
+use hellcall::core::vad::VoiceActivity;
+
+/// 统计分配次数的分配器
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// 模拟的声卡回调时长 (ms)
+const CALLBACK_MS: u32 = 10;
+/// 预热时长 (秒)，让各环节的缓冲区达到稳定大小
+const WARMUP_SECS: u32 = 2;
+/// 测量时长 (秒)
+const MEASURE_SECS: u32 = 60;
+
+/// 人声频段的正弦波叠加白噪声，i16 量级
+struct SyntheticSignal {
+    sample_rate: f32,
+    phase: f32,
+    seed: u32,
+}
+
+impl SyntheticSignal {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate as f32,
+            phase: 0.0,
+            seed: 0x1234_5678,
+        }
+    }
+
+    fn next(&mut self) -> f32 {
+        self.phase = (self.phase + 300.0 / self.sample_rate).fract();
+        self.seed = self
+            .seed
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        let noise = (self.seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5;
+        (self.phase * std::f32::consts::TAU).sin() * 8000.0 + noise * 1000.0
+    }
+}
+
+struct BenchResult {
+    elapsed: Duration,
+    allocations: usize,
+    callbacks: u32,
+}
+
+fn run(sample_rate: u32, enable_denoise: bool) -> BenchResult {
+    let frames_per_callback = (sample_rate * CALLBACK_MS / 1000) as usize;
+    let (mut producer, mut consumer) = sample_ring(sample_rate as usize * 2);
+    let config = AudioRecognizerConfig {
+        enable_denoise,
+        input_gain: 6.0,
+        enable_agc: true,
+        ..Default::default()
+    };
+    let activity = VoiceActivity::default();
+    let pipeline = config
+        .build_pipeline(&activity, &EventEmitter::new())
+        .unwrap();
+    let mut processor = ChunkProcessor::new(pipeline, config.samples_per_chunk());
+    processor.set_sample_rate(sample_rate).unwrap();
+    let mut signal = SyntheticSignal::new(sample_rate);
+
+    let mut step = || {
+        producer.push((0..frames_per_callback).map(|_| signal.next()));
+        processor
+            .process(&mut consumer, |chunk| {
+                activity.advance(chunk.len(), |_| {});
+                Ok(())
+            })
+            .unwrap();
+    };
+
+    let callbacks_per_sec = 1000 / CALLBACK_MS;
+    for _ in 0..WARMUP_SECS * callbacks_per_sec {
+        step();
+    }
+
+    let callbacks = MEASURE_SECS * callbacks_per_sec;
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..callbacks {
+        step();
+    }
+    let elapsed = start.elapsed();
+
+    BenchResult {
+        elapsed,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        callbacks,
+    }
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>8} {:>12} {:>14} {:>12}",
+        "rate", "denoise", "realtime", "per callback", "allocations"
+    );
+    for &(sample_rate, enable_denoise) in &[
+        (16000, false),
+        (44100, false),
+        (48000, false),
+        (44100, true),
+        (48000, true),
+    ] {
+        let result = run(sample_rate, enable_denoise);
+        let audio = Duration::from_secs(MEASURE_SECS as u64);
+        println!(
+            "{:>8} {:>8} {:>11.1}x {:>12.1}us {:>12}",
+            sample_rate,
+            enable_denoise,
+            audio.as_secs_f64() / result.elapsed.as_secs_f64(),
+            result.elapsed.as_secs_f64() * 1e6 / result.callbacks as f64,
+            result.allocations
+        );
+    }
+}
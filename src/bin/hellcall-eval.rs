@@ -0,0 +1,319 @@
+//! 离线评估指令识别准确率
+//!
+//! 把已标注的 WAV 录音依次送入与实时识别相同的处理流程 (重采样、降噪、增益、vosk 识别、指令匹配)，
+//! 统计每条指令的精确率/召回率、混淆矩阵和识别耗时，用于评估匹配阈值和语法字典的改动。
+//!
+//! 每条录音整段作为一句话识别，不经过 VAD 切分，也不受 `max_utterance_duration` 限制，
+//! 因此录音应当只包含一句指令，评估结果不反映 VAD 参数的影响。
+//!
+//! 录音按期望的指令分目录存放，目录名即指令名，`_none` 目录存放不应触发任何指令的录音:
+//!
+//! ```text
+//! samples/
+//!   增援/1.wav
+//!   补给/1.wav
+//!   _none/cough.wav
+//! ```
+//!
+//! 运行: `hellcall-eval <录音目录> [--config config.toml] [--model <vosk 模型路径>]`
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{env, fs};
+
+use anyhow::{Context, Result, anyhow};
+use vosk::LogLevel;
+
+use hellcall::Config;
+use hellcall::core::audio::{AudioRecognizer, AudioRecognizerConfig};
+use hellcall::core::matcher::{LevenshteinMatcher, MatchOutcome, match_result};
+
+/// 不应触发任何指令的录音所在目录
+const NONE_LABEL: &str = "_none";
+/// 报告中表示没有匹配到指令
+const NONE_DISPLAY: &str = "(none)";
+
+struct Args {
+    samples_dir: PathBuf,
+    config_path: String,
+    model_path: String,
+}
+
+impl Args {
+    fn parse() -> Result<Self> {
+        let mut samples_dir = None;
+        let mut config_path = env::var("HELLCALL_CONFIG_PATH").unwrap_or("config.toml".to_string());
+        let mut model_path = env::var("VOSK_MODEL_PATH").ok();
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => config_path = args.next().context("--config requires a path")?,
+                "--model" => model_path = Some(args.next().context("--model requires a path")?),
+                _ if arg.starts_with("--") => return Err(anyhow!("Unknown option: {}", arg)),
+                _ => samples_dir = Some(PathBuf::from(arg)),
+            }
+        }
+
+        Ok(Self {
+            samples_dir: samples_dir.context(
+                "Usage: hellcall-eval <samples dir> [--config config.toml] [--model <path>]",
+            )?,
+            config_path,
+            model_path: model_path.context("Set VOSK_MODEL_PATH or pass --model")?,
+        })
+    }
+}
+
+/// 一条录音的评估结果
+struct Sample {
+    path: PathBuf,
+    expected: Option<String>,
+    predicted: Option<String>,
+    text: String,
+    /// 录音时长
+    audio: Duration,
+    /// 从送完音频到得到最终结果的耗时
+    latency: Duration,
+    /// 处理整条录音的耗时
+    elapsed: Duration,
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    vosk::set_log_level(LogLevel::Error);
+
+    let args = Args::parse()?;
+    let content = fs::read_to_string(&args.config_path)
+        .with_context(|| format!("Failed to read config {}", args.config_path))?;
+    let config: Config = toml::from_str(&content)?;
+
+    let commands = config
+        .commands
+        .iter()
+        .map(|cmd| cmd.command.clone())
+        .collect::<Vec<_>>();
+    let mut matcher = LevenshteinMatcher::new(commands.clone());
+
+    let mut recognizer_config: AudioRecognizerConfig = config.recognizer.clone().into();
+    recognizer_config.set_grammar(config.grammar());
+    recognizer_config.set_recording(true);
+    let mut recognizer = AudioRecognizer::new(&args.model_path, recognizer_config)?;
+
+    let files = list_samples(&args.samples_dir)?;
+    if files.is_empty() {
+        return Err(anyhow!(
+            "No WAV files found under {}",
+            args.samples_dir.display()
+        ));
+    }
+
+    let mut samples = Vec::with_capacity(files.len());
+    for (path, expected) in files {
+        if let Some(label) = &expected
+            && !commands.contains(label)
+        {
+            eprintln!(
+                "warning: '{}' is not a configured command: {}",
+                label,
+                path.display()
+            );
+        }
+
+        let start = Instant::now();
+        let (sample_rate, pcm) = load_audio(&path, &recognizer.config)?;
+        let result = recognizer.recognize_offline(sample_rate, &pcm)?;
+        let elapsed = start.elapsed();
+
+        let text = result.as_ref().map(|r| r.text.clone()).unwrap_or_default();
+        let outcome = result.as_ref().map_or(MatchOutcome::Empty, |r| {
+            match_result(r, config.trigger.hit_word.as_deref(), &mut matcher)
+        });
+        let latency = result
+            .as_ref()
+            .and_then(|r| r.recording.as_ref())
+            .map(|r| Duration::from_millis(r.recognize_ms))
+            .unwrap_or_default();
+
+        samples.push(Sample {
+            path,
+            expected,
+            predicted: outcome.command().map(|c| c.to_string()),
+            text,
+            audio: Duration::from_secs_f64(pcm.len() as f64 / sample_rate as f64),
+            latency,
+            elapsed,
+        });
+    }
+
+    print_report(&samples);
+    Ok(())
+}
+
+/// 列出录音目录下所有 WAV 文件及其期望的指令
+fn list_samples(dir: &Path) -> Result<Vec<(PathBuf, Option<String>)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let label = entry.file_name().to_string_lossy().to_string();
+        let expected = (label != NONE_LABEL).then_some(label);
+        for file in fs::read_dir(entry.path())? {
+            let path = file?.path();
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"))
+            {
+                files.push((path, expected.clone()));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// 读取 WAV 并按配置的声道混合方式转换为单声道，返回采样率和 i16 量级的音频
+fn load_audio(path: &Path, config: &AudioRecognizerConfig) -> Result<(u32, Vec<f32>)> {
+    let mut reader = hound::WavReader::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .map(|s| s.map(|s| s * 32768.0))
+            .collect::<Result<Vec<_>, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 32768.0 / (1u32 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+
+    let channels = spec.channels as usize;
+    let weights = config.channel_mix.weights(channels)?;
+    let pcm = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().zip(&weights).map(|(s, w)| s * w).sum())
+        .collect::<Vec<f32>>();
+    Ok((spec.sample_rate, pcm))
+}
+
+fn label(command: &Option<String>) -> &str {
+    command.as_deref().unwrap_or(NONE_DISPLAY)
+}
+
+fn print_report(samples: &[Sample]) {
+    let correct = samples.iter().filter(|s| s.expected == s.predicted).count();
+    println!(
+        "Samples: {}, accuracy: {:.1}%",
+        samples.len(),
+        correct as f64 * 100.0 / samples.len() as f64
+    );
+
+    // 每条指令的精确率和召回率
+    let labels = samples
+        .iter()
+        .flat_map(|s| [s.expected.clone(), s.predicted.clone()])
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    println!();
+    println!(
+        "{:>4} {:>8} {:>10} {:>8}  command",
+        "#", "support", "precision", "recall"
+    );
+    for (i, command) in labels.iter().enumerate() {
+        let tp = samples
+            .iter()
+            .filter(|s| &s.expected == command && &s.predicted == command)
+            .count();
+        let support = samples.iter().filter(|s| &s.expected == command).count();
+        let predicted = samples.iter().filter(|s| &s.predicted == command).count();
+        println!(
+            "{:>4} {:>8} {:>10} {:>8}  {}",
+            i,
+            support,
+            ratio(tp, predicted),
+            ratio(tp, support),
+            label(command)
+        );
+    }
+
+    // 混淆矩阵，行为期望的指令，列为识别的指令，用上表中的序号表示
+    let mut matrix = BTreeMap::new();
+    for s in samples {
+        *matrix
+            .entry((s.expected.clone(), s.predicted.clone()))
+            .or_insert(0usize) += 1;
+    }
+    println!();
+    println!("Confusion matrix (rows: expected, columns: predicted)");
+    print!("{:>4}", "");
+    for i in 0..labels.len() {
+        print!(" {:>4}", i);
+    }
+    println!();
+    for (i, expected) in labels.iter().enumerate() {
+        print!("{:>4}", i);
+        for predicted in &labels {
+            match matrix.get(&(expected.clone(), predicted.clone())) {
+                Some(count) => print!(" {:>4}", count),
+                None => print!(" {:>4}", "."),
+            }
+        }
+        println!();
+    }
+
+    // 耗时
+    let mut latencies = samples.iter().map(|s| s.latency).collect::<Vec<_>>();
+    latencies.sort();
+    let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+    let audio = samples.iter().map(|s| s.audio).sum::<Duration>();
+    let elapsed = samples.iter().map(|s| s.elapsed).sum::<Duration>();
+    println!();
+    println!(
+        "Final result latency: mean {:?}, p50 {:?}, p95 {:?}, max {:?}",
+        latencies.iter().sum::<Duration>() / latencies.len() as u32,
+        percentile(50),
+        percentile(95),
+        percentile(100)
+    );
+    println!(
+        "Processed {:.1}s of audio in {:.1}s ({:.1}x realtime)",
+        audio.as_secs_f64(),
+        elapsed.as_secs_f64(),
+        audio.as_secs_f64() / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+
+    // 识别错误的录音
+    let errors = samples
+        .iter()
+        .filter(|s| s.expected != s.predicted)
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        println!();
+        println!("Errors:");
+        for s in errors {
+            println!(
+                "  {}: expected {}, got {} ({:?})",
+                s.path.display(),
+                label(&s.expected),
+                label(&s.predicted),
+                s.text
+            );
+        }
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> String {
+    if denominator == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.3}", numerator as f64 / denominator as f64)
+    }
+}
@@ -1,10 +1,15 @@
 #![allow(unused)]
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
 
-use crate::core::audio::AudioRecognizerConfig;
-use crate::core::keypress::{Input, KeyPresserConfig, LocalKey};
+pub use crate::core::audio::TalkMode;
+use crate::core::audio::{AudioRecognizerConfig, ChannelMix};
+use crate::core::keypress::{Hotkey, Input, KeyPresserConfig, LocalKey, MacroAction};
+use crate::core::speaker::{PlaybackOptions, PlaybackPolicy, SpeakerOptions, validate_audio_files};
+pub use crate::core::vad::{VadAggressiveness, VadEngine};
+use crate::utils::{StringOptionUtils, StringUtils};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Config {
@@ -26,6 +31,141 @@ pub struct Config {
     pub key_map: HashMap<LocalKey, Input>,
     pub trigger: TriggerConfig,
     pub commands: Vec<CommandConfig>,
+    /// 音频设备
+    ///
+    /// 示例:
+    /// ```toml
+    /// [device]
+    /// host = "WASAPI"
+    /// input = "wasapi:麦克风 (USB Audio Device)"
+    /// exclude_inputs = ["VB-Audio Virtual Cable"]
+    /// ```
+    #[serde(default)]
+    pub device: DeviceConfig,
+    /// 调试录音
+    ///
+    /// 示例:
+    /// ```toml
+    /// [recorder]
+    /// enable = true
+    /// dir = "recordings"
+    /// max_recordings = 100
+    /// ```
+    #[serde(default)]
+    pub recorder: RecorderConfig,
+    /// 提示音效的播放
+    ///
+    /// 示例:
+    /// ```toml
+    /// [speaker]
+    /// output = "wasapi:耳机 (USB Audio Device)"
+    /// volume = 1.7
+    /// speed = 1.05
+    /// policy = "interrupt"
+    /// ```
+    #[serde(default)]
+    pub speaker: SpeakerConfig,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DeviceConfig {
+    /// 音频后端，如 "ALSA"、"JACK"、"WASAPI"、"ASIO"，不填时使用系统默认后端
+    #[serde(default)]
+    pub host: Option<String>,
+    /// 麦克风的设备 ID 或名称，不填时自动选择
+    #[serde(default)]
+    pub input: Option<String>,
+    /// 自动选择麦克风时排除的设备，设备名包含其中任意一项 (不区分大小写) 即排除
+    #[serde(default = "default_exclude_inputs")]
+    pub exclude_inputs: Vec<String>,
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        Self {
+            host: None,
+            input: None,
+            exclude_inputs: default_exclude_inputs(),
+        }
+    }
+}
+
+fn default_exclude_inputs() -> Vec<String> {
+    vec!["VB-Audio Virtual Cable".to_string()]
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RecorderConfig {
+    /// 是否将每次识别的音频保存为 WAV，并附带记录识别和匹配结果的 JSON
+    #[serde(default)]
+    pub enable: bool,
+    /// 录音保存目录
+    #[serde(default = "default_recorder_dir")]
+    pub dir: String,
+    /// 最多保留的录音数，超出后删除最早的录音
+    #[serde(default = "default_max_recordings")]
+    pub max_recordings: usize,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            dir: default_recorder_dir(),
+            max_recordings: default_max_recordings(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SpeakerConfig {
+    /// 播放提示音效的输出设备 ID 或名称，不填时使用系统默认设备
+    #[serde(default)]
+    pub output: Option<String>,
+    /// 音量，1.0 为原始音量
+    #[serde(default = "default_speaker_volume")]
+    pub volume: f32,
+    /// 播放速度，1.0 为原始速度
+    #[serde(default = "default_speaker_speed")]
+    pub speed: f32,
+    /// 正在播放时又有新音效要播放的处理方式
+    #[serde(default)]
+    pub policy: PlaybackPolicy,
+    /// `queue` 策略下最多排队等待的音效数 (最少为 1)，队列满时丢弃最早排队的音效
+    #[serde(default = "default_max_queue")]
+    pub max_queue: usize,
+}
+
+impl Default for SpeakerConfig {
+    fn default() -> Self {
+        Self {
+            output: None,
+            volume: default_speaker_volume(),
+            speed: default_speaker_speed(),
+            policy: PlaybackPolicy::default(),
+            max_queue: default_max_queue(),
+        }
+    }
+}
+
+fn default_speaker_volume() -> f32 {
+    1.7
+}
+
+fn default_speaker_speed() -> f32 {
+    1.05
+}
+
+fn default_max_queue() -> usize {
+    3
+}
+
+fn default_recorder_dir() -> String {
+    "recordings".to_string()
+}
+
+fn default_max_recordings() -> usize {
+    100
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -40,15 +180,141 @@ pub struct RecognizerConfig {
     /// 语音识别的模式
     #[serde(default)]
     pub talk_mode: TalkMode,
+    /// 混合模式下松开 PTT 键后 VAD 继续生效的时间 (毫秒)
+    #[serde(default = "default_hybrid_listen_duration")]
+    pub hybrid_listen_duration: u64,
+    /// 按键说话时，按下按键前一并送去识别的音频时长 (毫秒)
+    #[serde(default = "default_ptt_pre_roll")]
+    pub ptt_pre_roll: u64,
+    /// 按键说话时，松开按键后继续送去识别的音频时长 (毫秒)
+    #[serde(default = "default_ptt_post_roll")]
+    pub ptt_post_roll: u64,
+    /// 语音活动检测的实现
+    #[serde(default)]
+    pub vad_engine: VadEngine,
+    /// WebRTC VAD 激进程度，越激进越不容易把噪音判断为语音
+    #[serde(default)]
+    pub vad_mode: VadAggressiveness,
+    /// VAD 每帧时长 (毫秒)，只能是 10、20 或 30
+    #[serde(default = "default_vad_frame_length")]
+    pub vad_frame_length: u64,
+    /// 连续超过多少帧为活动状态，认为开始说话
+    #[serde(default = "default_vad_start_frames")]
+    pub vad_start_frames: u32,
+    /// 连续超过多少帧为非活动状态，认为是静音
+    #[serde(default = "default_vad_end_frames")]
+    pub vad_end_frames: u32,
+    /// 音量 VAD 中人声相对底噪的倍数
+    #[serde(default = "default_energy_vad_ratio")]
+    pub energy_vad_ratio: f32,
+    /// Silero VAD 模型 (ONNX) 路径
+    #[serde(default)]
+    pub silero_vad_model: Option<String>,
+    /// Silero VAD 判断为人声的最低概率
+    #[serde(default = "default_silero_vad_threshold")]
+    pub silero_vad_threshold: f32,
+    /// 单次语音的最大时长 (毫秒)，超过后强制结束识别，0 表示不限制
+    #[serde(default = "default_max_utterance_duration")]
+    pub max_utterance_duration: u64,
+    /// 输入增益 (dB)，麦克风音量过小时可适当调高
+    #[serde(default)]
+    pub input_gain: f32,
+    /// 是否开启自动增益控制
+    #[serde(default)]
+    pub enable_agc: bool,
+    /// 自动增益控制的目标电平 (dBFS)
+    #[serde(default = "default_agc_target_level")]
+    pub agc_target_level: f32,
+    /// 自动增益控制最多额外提高的增益 (dB)
+    #[serde(default = "default_agc_max_gain")]
+    pub agc_max_gain: f32,
+    /// 只使用指定的输入声道 (从 0 开始)，不配置时所有声道取平均
+    #[serde(default)]
+    pub input_channel: Option<usize>,
+    /// 各输入声道的混合权重，配置后优先于 `input_channel`
+    #[serde(default)]
+    pub channel_weights: Option<Vec<f32>>,
+    /// 输入缓冲区能容纳的音频时长 (毫秒)，语音识别处理落后超过该时长时才会丢弃音频
+    #[serde(default = "default_input_buffer_duration")]
+    pub input_buffer_duration: u64,
+    /// 各语音识别模式下过滤短促噪音的规则，未配置的模式只丢弃空的识别结果
+    ///
+    /// VAD 容易被咳嗽、键盘声触发，如果指令都不止一个字，建议为 VAD 模式配置更严格的规则，例如:
+    /// ```toml
+    /// [recognizer.utterance_filter.voice_activation]
+    /// min_duration = 300
+    /// min_chars = 2
+    /// ```
+    #[serde(default)]
+    pub utterance_filter: HashMap<TalkMode, UtteranceFilterConfig>,
+}
+
+/// 未填写的项使用 `Default` 中的值，与库中 `AudioRecognizerConfig` 的默认值一致
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct UtteranceFilterConfig {
+    /// 最短语音时长 (毫秒)
+    pub min_duration: u64,
+    /// 识别结果最少字数 (不含空格)
+    pub min_chars: usize,
+}
+
+impl Default for UtteranceFilterConfig {
+    /// 默认只丢弃空的识别结果，不影响单字指令
+    fn default() -> Self {
+        Self {
+            min_duration: 0,
+            min_chars: 1,
+        }
+    }
+}
+
+fn default_hybrid_listen_duration() -> u64 {
+    3000
+}
+
+fn default_ptt_pre_roll() -> u64 {
+    300
+}
+
+fn default_ptt_post_roll() -> u64 {
+    200
+}
+
+fn default_vad_frame_length() -> u64 {
+    20
+}
+
+fn default_vad_start_frames() -> u32 {
+    3
+}
+
+fn default_vad_end_frames() -> u32 {
+    5
+}
+
+fn default_energy_vad_ratio() -> f32 {
+    3.0
+}
+
+fn default_silero_vad_threshold() -> f32 {
+    0.5
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
-pub enum TalkMode {
-    #[serde(rename = "push_to_talk")]
-    PushToTalk,
-    #[serde(rename = "voice_activation")]
-    #[default]
-    VoiceActivation,
+fn default_max_utterance_duration() -> u64 {
+    10000
+}
+
+fn default_agc_target_level() -> f32 {
+    -20.0
+}
+
+fn default_agc_max_gain() -> f32 {
+    20.0
+}
+
+fn default_input_buffer_duration() -> u64 {
+    2000
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -61,9 +327,125 @@ pub struct TriggerConfig {
 pub struct CommandConfig {
     pub command: String,
     pub grammar: Option<String>,
-    pub shortcut: Option<Input>,
+    /// 快捷键，支持组合键，例如 `["Alt", "Num1"]`
+    pub shortcut: Option<Hotkey>,
+    #[serde(default)]
     pub keys: Vec<LocalKey>,
+    /// 重新装填第 N 个最近执行的战备（1 表示最近一次），设置后忽略 `keys`
+    ///
+    /// 重新装填的战备执行后不会再次记入历史，各战备的序号不会因此变化。
+    ///
+    /// 示例:
+    /// ```toml
+    /// [[commands]]
+    /// command = "上上个"
+    /// shortcut = "F2"
+    /// recall = 2
+    /// audio_files = []
+    /// ```
+    #[serde(default)]
+    pub recall: Option<usize>,
     pub audio_files: Vec<String>,
+    /// 播放该指令音效的输出设备，不填时使用 `[speaker]` 中的设置
+    #[serde(default)]
+    pub output: Option<String>,
+    /// 播放该指令音效的音量，不填时使用 `[speaker]` 中的设置
+    #[serde(default)]
+    pub volume: Option<f32>,
+    /// 播放该指令音效的速度，不填时使用 `[speaker]` 中的设置
+    #[serde(default)]
+    pub speed: Option<f32>,
+}
+
+impl CommandConfig {
+    /// 指令触发的动作
+    pub fn action(&self) -> MacroAction {
+        match self.recall {
+            Some(index) => MacroAction::Recall(index),
+            None => MacroAction::Keys(self.keys.clone()),
+        }
+    }
+
+    /// 播放指令音效的设置
+    pub fn playback(&self) -> PlaybackOptions {
+        PlaybackOptions {
+            output: self.output.clone(),
+            volume: self.volume,
+            speed: self.speed,
+        }
+    }
+}
+
+impl Config {
+    /// 语音识别的语法字典：每条指令的 `grammar` (未设置时为逐字分隔的指令)，以及唤醒词
+    pub fn grammar(&self) -> Vec<String> {
+        let mut grammar: Vec<String> = self
+            .commands
+            .iter()
+            .map(|cmd| match &cmd.grammar {
+                Some(grammar) if !grammar.is_empty() => grammar.clone(),
+                _ => cmd.command.clone().add_between_chars(" "),
+            })
+            .collect();
+
+        let trigger = &self.trigger;
+        if let Some(hit_word_grammar) = trigger.hit_word_grammar.clone().filter(|g| !g.is_empty()) {
+            grammar.push(hit_word_grammar);
+        } else if !trigger.hit_word.is_empty() {
+            grammar.push(trigger.hit_word.clone().unwrap().add_between_chars(" "));
+        }
+        grammar
+    }
+
+    /// 配置中用到的所有音效文件
+    pub fn audio_files(&self) -> impl Iterator<Item = &String> {
+        let key_presser = &self.key_presser;
+        key_presser
+            .expired_audio_files
+            .iter()
+            .chain(&key_presser.enabled_audio_files)
+            .chain(&key_presser.disabled_audio_files)
+            .chain(self.commands.iter().flat_map(|cmd| &cmd.audio_files))
+    }
+
+    /// 检查 `audio_dir` 下用到的音效文件，移除无法播放的文件
+    ///
+    /// 无法播放的文件只记录到日志，不影响启动。
+    pub fn remove_invalid_audio_files(&mut self, audio_dir: &Path) {
+        let invalid = validate_audio_files(audio_dir, self.audio_files());
+        self.remove_audio_files(&invalid);
+    }
+
+    /// 从所有用到音效的地方移除 `invalid` 中的文件
+    pub fn remove_audio_files(&mut self, invalid: &BTreeSet<String>) {
+        if invalid.is_empty() {
+            return;
+        }
+        let key_presser = &mut self.key_presser;
+        let lists = [
+            &mut key_presser.expired_audio_files,
+            &mut key_presser.enabled_audio_files,
+            &mut key_presser.disabled_audio_files,
+        ];
+        for files in lists
+            .into_iter()
+            .chain(self.commands.iter_mut().map(|cmd| &mut cmd.audio_files))
+        {
+            files.retain(|file| !invalid.contains(file));
+        }
+    }
+
+    /// 提示音效的播放设置，与麦克风使用同一个音频后端
+    pub fn speaker_options(&self) -> SpeakerOptions {
+        SpeakerOptions {
+            host: self.device.host.clone(),
+            output: self.speaker.output.clone(),
+            volume: self.speaker.volume,
+            speed: self.speaker.speed,
+            policy: self.speaker.policy,
+            max_queue: self.speaker.max_queue,
+        }
+    }
 }
 
 impl Default for Config {
@@ -82,6 +464,9 @@ impl Default for Config {
             ]),
             trigger: TriggerConfig::default(),
             commands: Vec::new(),
+            device: DeviceConfig::default(),
+            recorder: RecorderConfig::default(),
+            speaker: SpeakerConfig::default(),
         }
     }
 }
@@ -93,6 +478,26 @@ impl Default for RecognizerConfig {
             vad_silence_duration: 200,
             enable_denoise: false,
             talk_mode: TalkMode::VoiceActivation,
+            hybrid_listen_duration: default_hybrid_listen_duration(),
+            ptt_pre_roll: default_ptt_pre_roll(),
+            ptt_post_roll: default_ptt_post_roll(),
+            vad_engine: VadEngine::default(),
+            vad_mode: VadAggressiveness::default(),
+            vad_frame_length: default_vad_frame_length(),
+            vad_start_frames: default_vad_start_frames(),
+            vad_end_frames: default_vad_end_frames(),
+            energy_vad_ratio: default_energy_vad_ratio(),
+            silero_vad_model: None,
+            silero_vad_threshold: default_silero_vad_threshold(),
+            max_utterance_duration: default_max_utterance_duration(),
+            input_gain: 0.0,
+            enable_agc: false,
+            agc_target_level: default_agc_target_level(),
+            agc_max_gain: default_agc_max_gain(),
+            input_channel: None,
+            channel_weights: None,
+            input_buffer_duration: default_input_buffer_duration(),
+            utterance_filter: HashMap::new(),
         }
     }
 }
@@ -108,12 +513,44 @@ impl Default for TriggerConfig {
 
 impl Into<AudioRecognizerConfig> for RecognizerConfig {
     fn into(self) -> AudioRecognizerConfig {
+        let utterance_filter = self
+            .utterance_filter
+            .get(&self.talk_mode)
+            .copied()
+            .unwrap_or_default();
+        let channel_mix = match (self.channel_weights, self.input_channel) {
+            (Some(weights), _) => ChannelMix::Weights(weights),
+            (None, Some(channel)) => ChannelMix::Channel(channel),
+            (None, None) => ChannelMix::Average,
+        };
+
         AudioRecognizerConfig {
             chunk_time: self.chunk_time,
             grammar: Vec::new(),
             vad_silence_duration: self.vad_silence_duration,
             enable_denoise: self.enable_denoise,
-            is_ptt: self.talk_mode == TalkMode::PushToTalk,
+            talk_mode: self.talk_mode,
+            hybrid_listen_duration: self.hybrid_listen_duration,
+            ptt_pre_roll: self.ptt_pre_roll,
+            ptt_post_roll: self.ptt_post_roll,
+            vad_engine: self.vad_engine,
+            vad_mode: self.vad_mode,
+            vad_frame_length: self.vad_frame_length,
+            vad_start_frames: self.vad_start_frames,
+            vad_end_frames: self.vad_end_frames,
+            energy_vad_ratio: self.energy_vad_ratio,
+            silero_vad_model: self.silero_vad_model,
+            silero_vad_threshold: self.silero_vad_threshold,
+            max_utterance_duration: self.max_utterance_duration,
+            min_utterance_duration: utterance_filter.min_duration,
+            min_utterance_chars: utterance_filter.min_chars,
+            input_gain: self.input_gain,
+            enable_agc: self.enable_agc,
+            agc_target_level: self.agc_target_level,
+            agc_max_gain: self.agc_max_gain,
+            channel_mix,
+            input_buffer_duration: self.input_buffer_duration,
+            record_utterances: false,
         }
     }
 }
@@ -1,14 +1,53 @@
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::TryRecvError;
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use log::{debug, info};
+use cpal::traits::{DeviceTrait, HostTrait};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
 use vosk::{Model, Recognizer};
-use webrtc_vad::{SampleRate, Vad, VadMode};
+
+use crate::core::device::get_host;
+use crate::core::event::{EventEmitter, HellcallEvent};
+use crate::core::gain::{GainStage, InputGain};
+use crate::core::input::{
+    InputMessage, InputOptions, InputStats, InputStatsMonitor, InputStreamSupervisor,
+};
+use crate::core::pipeline::AudioPipeline;
+use crate::core::recorder::UtteranceRecording;
+use crate::core::ring::RingConsumer;
+use crate::core::vad::*;
 
 static VOSK_SAMPLE_RATE: f32 = 16000.0;
+/// 处理线程没有数据可处理时的等待间隔
+const PROCESS_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TalkMode {
+    /// 按住 PTT 键说话
+    #[serde(rename = "push_to_talk")]
+    PushToTalk,
+    /// 按一下 PTT 键开始说话，再按一下结束
+    #[serde(rename = "toggle_to_talk")]
+    ToggleToTalk,
+    /// 由 VAD 自动判断说话
+    #[serde(rename = "voice_activation")]
+    #[default]
+    VoiceActivation,
+    /// 由 VAD 判断说话，但只在按住 PTT 键期间或松开后一段时间内生效
+    #[serde(rename = "hybrid")]
+    Hybrid,
+}
+
+impl TalkMode {
+    /// 是否完全由按键控制说话状态，不经过 VAD
+    pub fn is_manual(&self) -> bool {
+        matches!(self, TalkMode::PushToTalk | TalkMode::ToggleToTalk)
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct AudioRecognizerConfig {
@@ -20,8 +59,50 @@ pub struct AudioRecognizerConfig {
     pub vad_silence_duration: u64,
     /// 是否开启降噪
     pub enable_denoise: bool,
-    /// 是否为按键说话模式
-    pub is_ptt: bool,
+    /// 语音识别的模式
+    pub talk_mode: TalkMode,
+    /// 混合模式下松开 PTT 键后 VAD 继续生效的时间 (ms)
+    pub hybrid_listen_duration: u64,
+    /// 按键说话时，按下按键前一并送去识别的音频时长 (ms)
+    pub ptt_pre_roll: u64,
+    /// 按键说话时，松开按键后继续送去识别的音频时长 (ms)
+    pub ptt_post_roll: u64,
+    /// 语音活动检测的实现
+    pub vad_engine: VadEngine,
+    /// WebRTC VAD 激进程度
+    pub vad_mode: VadAggressiveness,
+    /// VAD 每帧时长 (ms)，只能是 10、20 或 30
+    pub vad_frame_length: u64,
+    /// 连续超过多少帧为活动状态，认为开始说话
+    pub vad_start_frames: u32,
+    /// 连续超过多少帧为非活动状态，认为是静音
+    pub vad_end_frames: u32,
+    /// 音量 VAD 中人声相对底噪的倍数
+    pub energy_vad_ratio: f32,
+    /// Silero VAD 模型路径
+    pub silero_vad_model: Option<String>,
+    /// Silero VAD 判断为人声的最低概率
+    pub silero_vad_threshold: f32,
+    /// 单次语音的最大时长 (ms)，超过后强制结束识别，0 表示不限制
+    pub max_utterance_duration: u64,
+    /// 最短语音时长 (ms)，更短的最终结果会被过滤，不触发指令
+    pub min_utterance_duration: u64,
+    /// 最终结果的最少字数 (不含空格)，更少的最终结果会被过滤，默认为 1，即只过滤空结果
+    pub min_utterance_chars: usize,
+    /// 输入增益 (dB)
+    pub input_gain: f32,
+    /// 是否开启自动增益控制
+    pub enable_agc: bool,
+    /// 自动增益控制的目标电平 (dBFS)
+    pub agc_target_level: f32,
+    /// 自动增益控制最多额外提高的增益 (dB)
+    pub agc_max_gain: f32,
+    /// 多声道输入合并为单声道的方式
+    pub channel_mix: ChannelMix,
+    /// 输入缓冲区能容纳的音频时长 (ms)，处理线程落后超过该时长时丢弃采样
+    pub input_buffer_duration: u64,
+    /// 是否保留每次语音送去识别的音频和中间结果，供调试录音使用
+    pub record_utterances: bool,
 }
 
 impl Default for AudioRecognizerConfig {
@@ -31,7 +112,28 @@ impl Default for AudioRecognizerConfig {
             grammar: Vec::new(),
             vad_silence_duration: 500,
             enable_denoise: false,
-            is_ptt: false,
+            talk_mode: TalkMode::VoiceActivation,
+            hybrid_listen_duration: 3000,
+            ptt_pre_roll: 300,
+            ptt_post_roll: 200,
+            vad_engine: VadEngine::WebRtc,
+            vad_mode: VadAggressiveness::Aggressive,
+            vad_frame_length: 20,
+            vad_start_frames: 3,
+            vad_end_frames: 5,
+            energy_vad_ratio: 3.0,
+            silero_vad_model: None,
+            silero_vad_threshold: 0.5,
+            max_utterance_duration: 10000,
+            min_utterance_duration: 0,
+            min_utterance_chars: 1,
+            input_gain: 0.0,
+            enable_agc: false,
+            agc_target_level: -20.0,
+            agc_max_gain: 20.0,
+            channel_mix: ChannelMix::Average,
+            input_buffer_duration: 2000,
+            record_utterances: false,
         }
     }
 }
@@ -40,12 +142,166 @@ impl AudioRecognizerConfig {
     pub fn set_grammar(&mut self, grammar: Vec<String>) {
         self.grammar = grammar;
     }
+
+    pub fn set_recording(&mut self, record_utterances: bool) {
+        self.record_utterances = record_utterances;
+    }
+
+    /// 按配置创建输入增益处理器
+    pub fn create_input_gain(&self) -> InputGain {
+        let agc_target = self.enable_agc.then_some(self.agc_target_level);
+        InputGain::new(self.input_gain, agc_target, self.agc_max_gain)
+    }
+
+    /// 最终结果是否足够长，过滤咳嗽、键盘声等误触发 VAD 的短促噪音
+    ///
+    /// `duration` 为语音时长 (ms)，字数不含空格。
+    pub fn is_long_enough(&self, text: &str, duration: u64) -> bool {
+        let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+        duration >= self.min_utterance_duration && chars >= self.min_utterance_chars
+    }
+
+    /// 每个识别 chunk 的采样数
+    pub fn samples_per_chunk(&self) -> usize {
+        ((self.chunk_time * VOSK_SAMPLE_RATE) as usize).max(1)
+    }
+
+    /// 按配置构建音频处理管线，实时识别、离线识别和基准测试共用:
+    ///
+    /// 设备采样率 -> (48k -> 降噪 ->) 16k -> 增益 -> VAD -> 识别
+    ///
+    /// 按键说话模式下没有 VAD。要在识别前加入其它处理 (例如高通滤波)，在增益之前插入即可。
+    /// 设备采样率由 `AudioPipeline::set_sample_rate` 设置，VAD 的检测结果写入 `activity`。
+    pub fn build_pipeline(
+        &self,
+        activity: &VoiceActivity,
+        events: &EventEmitter,
+    ) -> Result<AudioPipeline> {
+        let mut pipeline = AudioPipeline::new(self.enable_denoise);
+        pipeline.push(GainStage::new(
+            self.create_input_gain(),
+            self.samples_per_chunk(),
+            events.clone(),
+        ));
+        if !self.talk_mode.is_manual() {
+            pipeline.push(VadStage::new(
+                self.create_voice_detector()?,
+                activity.clone(),
+            ));
+        }
+        Ok(pipeline)
+    }
+
+    /// 按配置创建语音活动检测器
+    pub fn create_voice_detector(&self) -> Result<Box<dyn VoiceDetector>> {
+        match self.vad_engine {
+            VadEngine::WebRtc => Ok(Box::new(WebRtcVad::new(
+                self.vad_mode,
+                self.vad_frame_length,
+            ))),
+            VadEngine::Energy => Ok(Box::new(EnergyVad::new(
+                self.energy_vad_ratio,
+                self.vad_frame_length,
+            ))),
+            #[cfg(feature = "silero-vad")]
+            VadEngine::Silero => {
+                let model_path = self
+                    .silero_vad_model
+                    .as_deref()
+                    .context("silero_vad_model must be set to use Silero VAD")?;
+                Ok(Box::new(SileroVad::new(
+                    model_path,
+                    self.silero_vad_threshold,
+                )?))
+            }
+            #[cfg(not(feature = "silero-vad"))]
+            VadEngine::Silero => Err(anyhow::anyhow!(
+                "Silero VAD is not available, rebuild with the `silero-vad` feature"
+            )),
+        }
+    }
+}
+
+/// 多声道输入合并为单声道的方式
+#[derive(Debug, Clone, Default)]
+pub enum ChannelMix {
+    /// 所有声道取平均
+    #[default]
+    Average,
+    /// 只使用指定的声道 (从 0 开始)
+    Channel(usize),
+    /// 按权重混合各声道
+    Weights(Vec<f32>),
+}
+
+impl ChannelMix {
+    /// 计算每个声道的权重
+    pub fn weights(&self, channels: usize) -> Result<Vec<f32>> {
+        match self {
+            ChannelMix::Average => Ok(vec![1.0 / channels as f32; channels]),
+            ChannelMix::Channel(channel) => {
+                if *channel >= channels {
+                    return Err(anyhow::anyhow!(
+                        "Input channel {} is out of range, the device has {} channels",
+                        channel,
+                        channels
+                    ));
+                }
+                let mut weights = vec![0.0; channels];
+                weights[*channel] = 1.0;
+                Ok(weights)
+            }
+            ChannelMix::Weights(weights) => {
+                if weights.len() != channels {
+                    warn!(
+                        "channel_weights has {} entries but the device has {} channels",
+                        weights.len(),
+                        channels
+                    );
+                }
+                let mut weights = weights.clone();
+                weights.resize(channels, 0.0);
+                Ok(weights)
+            }
+        }
+    }
+}
+
+/// 混合模式下 VAD 的开关：按住 PTT 键期间，以及松开后一段时间内打开
+#[derive(Debug, Default)]
+struct TalkGate {
+    held: AtomicBool,
+    open_until: Mutex<Option<Instant>>,
+}
+
+impl TalkGate {
+    fn is_open(&self) -> bool {
+        self.held.load(Ordering::Acquire)
+            || self
+                .open_until
+                .lock()
+                .unwrap()
+                .is_some_and(|until| Instant::now() < until)
+    }
+
+    fn set_held(&self, held: bool, listen_duration: Duration) {
+        self.held.store(held, Ordering::Release);
+        if !held {
+            *self.open_until.lock().unwrap() = Some(Instant::now() + listen_duration);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct RecognitionResult {
     pub text: String,
     pub is_partial: bool,
+    /// 语音时长 (ms)
+    pub duration: u64,
+    /// 本次语音的录音，只有开启录音时的最终结果才有
+    pub recording: Option<Arc<UtteranceRecording>>,
+    /// 语音过短或字数过少，被当作噪音过滤，不应触发指令
+    pub filtered: bool,
 }
 
 pub struct AudioRecognizer {
@@ -55,8 +311,28 @@ pub struct AudioRecognizer {
     is_speaking: Arc<AtomicBool>,
     silence_start: Mutex<Option<std::time::Instant>>,
     is_finalized: Arc<AtomicBool>,
+    /// 暂停识别，暂停期间丢弃所有音频
+    is_paused: Arc<AtomicBool>,
+    talk_gate: Arc<TalkGate>,
     audio_cache: Vec<i16>,
     max_cache_samples: usize,
+    post_roll_samples: usize,
+    /// 松开按键后已送去识别的音频采样数
+    post_roll_fed: usize,
+    /// VAD 连续活动帧数，跨 chunk 累计
+    active_frames: u32,
+    /// VAD 连续非活动帧数，跨 chunk 累计
+    non_active_frames: u32,
+    /// 管线中 VAD 环节的检测结果
+    voice_activity: VoiceActivity,
+    max_utterance_samples: usize,
+    /// 本次语音已送去识别的音频采样数
+    utterance_samples: usize,
+    /// 本次语音超过最大时长被强制结束
+    truncated: bool,
+    /// 本次语音的录音
+    recording: Option<UtteranceRecording>,
+    events: EventEmitter,
 }
 
 impl Clone for AudioRecognizer {
@@ -72,8 +348,20 @@ impl Clone for AudioRecognizer {
             is_speaking: Arc::clone(&self.is_speaking),
             silence_start: Mutex::new(self.silence_start.lock().unwrap().clone()),
             is_finalized: Arc::clone(&self.is_finalized),
+            is_paused: Arc::clone(&self.is_paused),
+            talk_gate: Arc::clone(&self.talk_gate),
             audio_cache: self.audio_cache.clone(),
             max_cache_samples: self.max_cache_samples,
+            post_roll_samples: self.post_roll_samples,
+            post_roll_fed: self.post_roll_fed,
+            active_frames: self.active_frames,
+            non_active_frames: self.non_active_frames,
+            voice_activity: self.voice_activity.clone(),
+            max_utterance_samples: self.max_utterance_samples,
+            utterance_samples: self.utterance_samples,
+            truncated: self.truncated,
+            recording: self.recording.clone(),
+            events: self.events.clone(),
         }
     }
 }
@@ -85,10 +373,25 @@ impl AudioRecognizer {
         let recognizer = Recognizer::new_with_grammar(&model, VOSK_SAMPLE_RATE, &config.grammar)
             .context("Failed to create Vosk recognizer")?;
 
-        let samples_per_frame = VOSK_SAMPLE_RATE as usize * 20 / 1000;
-        let vad_samples = 4 * samples_per_frame;
-        let chunk_samples = (config.chunk_time * VOSK_SAMPLE_RATE) as usize;
-        let max_cache_samples = chunk_samples + vad_samples;
+        if !matches!(config.vad_frame_length, 10 | 20 | 30) {
+            return Err(anyhow::anyhow!(
+                "VAD frame length must be 10, 20 or 30 ms, got {}",
+                config.vad_frame_length
+            ));
+        }
+
+        let ms_to_samples = |ms: u64| (ms as f32 * VOSK_SAMPLE_RATE / 1000.0) as usize;
+        // 按键说话时缓存 pre-roll，VAD 模式下缓存触发语音前的若干帧
+        let max_cache_samples = if config.talk_mode.is_manual() {
+            ms_to_samples(config.ptt_pre_roll)
+        } else {
+            let samples_per_frame = config.vad_engine.frame_samples(config.vad_frame_length);
+            let vad_samples = (config.vad_start_frames as usize + 1) * samples_per_frame;
+            let chunk_samples = (config.chunk_time * VOSK_SAMPLE_RATE) as usize;
+            chunk_samples + vad_samples
+        };
+        let post_roll_samples = ms_to_samples(config.ptt_post_roll);
+        let max_utterance_samples = ms_to_samples(config.max_utterance_duration);
 
         Ok(Self {
             model: Arc::new(model),
@@ -97,30 +400,98 @@ impl AudioRecognizer {
             is_speaking: Arc::new(AtomicBool::new(false)),
             silence_start: Mutex::new(None),
             is_finalized: Arc::new(AtomicBool::new(false)),
+            is_paused: Arc::new(AtomicBool::new(false)),
+            talk_gate: Arc::new(TalkGate::default()),
             audio_cache: Vec::with_capacity(max_cache_samples),
             max_cache_samples,
+            post_roll_samples,
+            post_roll_fed: 0,
+            active_frames: 0,
+            non_active_frames: 0,
+            voice_activity: VoiceActivity::default(),
+            max_utterance_samples,
+            utterance_samples: 0,
+            truncated: false,
+            recording: None,
+            events: EventEmitter::new(),
         })
     }
 
+    /// 设置事件分发器
+    pub fn set_events(&mut self, events: EventEmitter) {
+        self.events = events;
+    }
+
     pub fn process_audio_chunk(
         &mut self,
         audio_chunk: &[i16],
     ) -> Result<Option<RecognitionResult>> {
-        if self.is_speaking.load(Ordering::Acquire) {
+        if self.is_paused.load(Ordering::Acquire) {
+            // 丢弃暂停前未说完的语音
+            if self.is_speaking.load(Ordering::Acquire) || !self.audio_cache.is_empty() {
+                self.reset();
+            }
+            return Ok(None);
+        }
+
+        let is_speaking = self.is_speaking.load(Ordering::Acquire);
+        let is_post_rolling = !is_speaking && self.is_post_rolling();
+        if is_speaking || is_post_rolling {
+            if is_post_rolling {
+                self.post_roll_fed += audio_chunk.len();
+            }
+
+            if self.config.record_utterances {
+                let recording = self.recording.get_or_insert_with(UtteranceRecording::new);
+                recording.samples.extend_from_slice(&self.audio_cache);
+                recording.samples.extend_from_slice(audio_chunk);
+            }
+
             if !self.audio_cache.is_empty() {
                 self.recognizer
                     .accept_waveform(&self.audio_cache)
                     .context("Failed to accept cached waveform")?;
+                self.utterance_samples += self.audio_cache.len();
                 self.audio_cache.clear();
             }
 
             self.recognizer
                 .accept_waveform(audio_chunk)
                 .context("Failed to accept waveform")?;
+            self.utterance_samples += audio_chunk.len();
+
+            // VAD 迟迟等不到静音 (例如游戏声音漏进麦克风) 或按键一直按住时强制结束，避免无限累积音频
+            if self.max_utterance_samples > 0
+                && self.utterance_samples >= self.max_utterance_samples
+                && !self.is_finalized.load(Ordering::Acquire)
+            {
+                let duration = self.utterance_duration();
+                warn!(
+                    "utterance exceeds max_utterance_duration ({} ms), force finalize",
+                    self.config.max_utterance_duration
+                );
+                if self.config.talk_mode.is_manual() {
+                    // 按键仍按住或处于开启状态，结束这段语音后继续识别，不等待 post-roll
+                    self.post_roll_fed = self.post_roll_samples;
+                } else {
+                    self.is_speaking.store(false, Ordering::Release);
+                    *self.silence_start.lock().unwrap() = None;
+                }
+                self.truncated = true;
+                self.is_finalized.store(true, Ordering::Release);
+                self.events
+                    .emit(HellcallEvent::UtteranceTruncated { duration });
+            }
             let result = self.recognizer.partial_result();
+            if let Some(recording) = &mut self.recording {
+                recording.push_partial(result.partial);
+            }
             let result = RecognitionResult {
                 text: result.partial.to_string(),
                 is_partial: true,
+                duration: self.utterance_duration(),
+                recording: None,
+                filtered: false,
             };
 
             debug!("partial result: {:?}", result);
@@ -137,69 +508,145 @@ impl AudioRecognizer {
         Ok(None)
     }
 
+    /// 按配置构建音频处理管线，VAD 的检测结果由本识别器取用
+    pub fn create_pipeline(&self) -> Result<AudioPipeline> {
+        self.config
+            .build_pipeline(&self.voice_activity, &self.events)
+    }
+
+    /// 离线识别一段完整的语音，不经过 VAD 和按键判断，返回最终结果
+    ///
+    /// `pcm` 是采样率为 `sample_rate` 的单声道音频 (i16 量级)，与实时识别经过相同的管线，
+    /// 再按 `chunk_time` 切分后整段送去识别。整段音频视为一句话，不受 `max_utterance_duration` 限制。
+    pub fn recognize_offline(
+        &mut self,
+        sample_rate: u32,
+        pcm: &[f32],
+    ) -> Result<Option<RecognitionResult>> {
+        self.reset();
+        let mut pipeline = self.create_pipeline()?;
+        pipeline.set_sample_rate(sample_rate)?;
+        let mut audio = Vec::new();
+        pipeline.process(pcm, &mut audio)?;
+        pipeline.flush(&mut audio)?;
+
+        // 关闭语音时长上限，否则超长的录音会在中途被强制结束，后半段不会被识别
+        let max_utterance_samples = std::mem::take(&mut self.max_utterance_samples);
+        self.is_speaking.store(true, Ordering::Release);
+        let mut chunk = Vec::with_capacity(self.config.samples_per_chunk());
+        let result = audio
+            .chunks(self.config.samples_per_chunk())
+            .try_for_each(|samples| {
+                chunk.clear();
+                chunk.extend(
+                    samples
+                        .iter()
+                        .map(|&sample| sample.clamp(-32768.0, 32767.0) as i16),
+                );
+                self.process_audio_chunk(&chunk).map(|_| ())
+            });
+        self.max_utterance_samples = max_utterance_samples;
+        result?;
+
+        self.is_speaking.store(false, Ordering::Release);
+        self.is_finalized.store(true, Ordering::Release);
+        self.post_roll_fed = self.post_roll_samples;
+        self.finalize()
+    }
+
+    /// 按键说话模式下已松开按键，但 post-roll 音频还未送完
+    fn is_post_rolling(&self) -> bool {
+        self.config.talk_mode.is_manual()
+            && self.is_finalized.load(Ordering::Acquire)
+            && self.post_roll_fed < self.post_roll_samples
+    }
+
     pub fn finalize(&mut self) -> Result<Option<RecognitionResult>> {
-        if !self.is_finalized.load(Ordering::Acquire) {
+        if !self.is_finalized.load(Ordering::Acquire) || self.is_post_rolling() {
             return Ok(None);
         }
 
+        let recognize_start = Instant::now();
         let result = self.recognizer.final_result();
-        let recognition_result = RecognitionResult {
+        let recording = self.recording.take().map(|mut recording| {
+            recording.recognize_ms = recognize_start.elapsed().as_millis() as u64;
+            Arc::new(recording)
+        });
+        let mut recognition_result = RecognitionResult {
             text: result
                 .single()
                 .context("Failed to get final result")?
                 .text
                 .to_string(),
             is_partial: false,
+            duration: self.utterance_duration(),
+            recording,
+            filtered: false,
         };
 
-        self.reset();
+        // 按键说话模式下强制结束的语音，说话状态由按键决定，不在这里清除
+        if self.truncated && self.config.talk_mode.is_manual() {
+            self.reset_utterance();
+        } else {
+            self.reset();
+        }
 
+        // 过短的结果仍然返回，以便录音器保存下来用于调整过滤阈值
+        recognition_result.filtered = !self
+            .config
+            .is_long_enough(&recognition_result.text, recognition_result.duration);
         debug!("final result: {:?}", recognition_result);
 
         Ok(Some(recognition_result))
     }
 
-    /// 检测语音活动
-    pub fn detect_speech(&mut self, audio_chunk: &[i16], vad: &mut Vad) -> Result<()> {
-        if self.config.is_ptt {
-            return Ok(());
-        }
+    /// 本次语音已送去识别的时长 (ms)
+    fn utterance_duration(&self) -> u64 {
+        self.utterance_samples as u64 * 1000 / VOSK_SAMPLE_RATE as u64
+    }
 
-        if audio_chunk.is_empty() {
-            return Ok(());
+    /// 根据管线中 VAD 环节对这个 chunk 的检测结果更新语音状态
+    ///
+    /// 需要按顺序对送去识别的每个 chunk 调用。
+    pub fn detect_speech(&mut self, audio_chunk: &[i16]) {
+        if self.config.talk_mode.is_manual() {
+            return;
         }
 
-        // 切分帧
-        // 采样率 * 每帧时间(ms) / 1000
-        let samples_per_frame = VOSK_SAMPLE_RATE as usize * 20 / 1000;
+        // 暂停时忽略检测结果；混合模式下 VAD 关闭时不开始新的语音，但已开始的语音仍由 VAD 判断结束
+        let ignored = self.is_paused.load(Ordering::Acquire)
+            || (self.config.talk_mode == TalkMode::Hybrid
+                && !self.is_speaking.load(Ordering::Acquire)
+                && !self.talk_gate.is_open());
 
-        // 连续帧
-        let mut active_frames = 0;
-        let mut non_active_frames = 0;
-        for frame in audio_chunk.chunks_exact(samples_per_frame) {
-            let is_active = vad
-                .is_voice_segment(frame)
-                .map_err(|e| anyhow::anyhow!("Failed to detect speech: {:?}", e))?;
-
-            if is_active {
-                active_frames += 1;
-                non_active_frames = 0;
-            } else {
-                active_frames = 0;
-                non_active_frames += 1;
+        let activity = self.voice_activity.clone();
+        activity.advance(audio_chunk.len(), |is_active| {
+            if !ignored {
+                self.handle_vad_frame(is_active);
             }
+        });
+    }
 
-            // 连续 3 帧为活动状态，认为是语音
-            if active_frames > 3 {
-                self.update_speech_state(true);
-            }
+    /// 按连续的活动帧和非活动帧数更新语音状态
+    fn handle_vad_frame(&mut self, is_active: bool) {
+        // 连续帧
+        if is_active {
+            self.active_frames += 1;
+            self.non_active_frames = 0;
+        } else {
+            self.active_frames = 0;
+            self.non_active_frames += 1;
+        }
 
-            // 连续 5 帧为非活动状态，认为是静音
-            if non_active_frames > 5 {
-                self.update_speech_state(false);
-            }
+        // 连续超过 vad_start_frames 帧为活动状态，认为是语音
+        if self.active_frames > self.config.vad_start_frames {
+            self.update_speech_state(true);
+        }
+
+        // 连续超过 vad_end_frames 帧为非活动状态，认为是静音
+        if self.non_active_frames > self.config.vad_end_frames {
+            self.update_speech_state(false);
         }
-        Ok(())
     }
 
     /// 更新语音状态
@@ -227,11 +674,22 @@ impl AudioRecognizer {
     }
 
     pub fn reset(&mut self) {
-        self.recognizer.reset();
         self.is_speaking.store(false, Ordering::Release);
+        self.reset_utterance();
+    }
+
+    /// 清除本次语音的识别状态，保留说话状态
+    fn reset_utterance(&mut self) {
+        self.recognizer.reset();
         *self.silence_start.lock().unwrap() = None;
         self.is_finalized.store(false, Ordering::Release);
         self.audio_cache.clear();
+        self.post_roll_fed = 0;
+        self.active_frames = 0;
+        self.non_active_frames = 0;
+        self.utterance_samples = 0;
+        self.truncated = false;
+        self.recording = None;
     }
 }
 
@@ -239,6 +697,10 @@ impl AudioRecognizer {
 pub struct AudioSpeechController {
     is_speaking: Option<Arc<AtomicBool>>,
     is_finalized: Option<Arc<AtomicBool>>,
+    is_paused: Option<Arc<AtomicBool>>,
+    talk_gate: Option<Arc<TalkGate>>,
+    talk_mode: TalkMode,
+    hybrid_listen_duration: u64,
 }
 
 impl AudioSpeechController {
@@ -252,62 +714,183 @@ impl AudioSpeechController {
             }
         }
     }
+
+    pub fn is_speaking(&self) -> bool {
+        self.is_speaking
+            .as_ref()
+            .is_some_and(|is_speaking| is_speaking.load(Ordering::Acquire))
+    }
+
+    /// 处理 PTT 键的按下和松开，`pressed` 为 `true` 表示按下
+    pub fn handle_talk_key(&self, pressed: bool) {
+        match self.talk_mode {
+            TalkMode::PushToTalk | TalkMode::VoiceActivation => self.set_is_speaking(pressed),
+            TalkMode::ToggleToTalk => {
+                if pressed {
+                    self.set_is_speaking(!self.is_speaking());
+                }
+            }
+            TalkMode::Hybrid => {
+                if let Some(talk_gate) = &self.talk_gate {
+                    talk_gate.set_held(pressed, Duration::from_millis(self.hybrid_listen_duration));
+                }
+            }
+        }
+    }
+
+    /// 暂停或恢复语音识别
+    pub fn set_paused(&self, paused: bool) {
+        if let Some(is_paused) = &self.is_paused {
+            is_paused.store(paused, Ordering::Release);
+        }
+    }
+}
+
+/// 处理线程的热路径：从环形缓冲区取出设备采样，经过管线后转换为 i16 并按 chunk 切分
+///
+/// 各缓冲区跨调用复用，达到稳定大小后不再分配内存。实时识别和基准测试共用。
+pub struct ChunkProcessor {
+    pipeline: AudioPipeline,
+    samples_per_chunk: usize,
+    pcm: Vec<f32>,
+    f32_buffer: Vec<f32>,
+    i16_buffer: Vec<i16>,
+    chunk: Vec<i16>,
+}
+
+impl ChunkProcessor {
+    pub fn new(pipeline: AudioPipeline, samples_per_chunk: usize) -> Self {
+        Self {
+            pipeline,
+            samples_per_chunk,
+            pcm: Vec::new(),
+            f32_buffer: Vec::new(),
+            i16_buffer: Vec::new(),
+            chunk: Vec::with_capacity(samples_per_chunk),
+        }
+    }
+
+    /// 设置设备采样率，丢弃管线中和未凑满 chunk 的旧数据
+    pub fn set_sample_rate(&mut self, sample_rate: u32) -> Result<()> {
+        self.pipeline.set_sample_rate(sample_rate)?;
+        self.i16_buffer.clear();
+        Ok(())
+    }
+
+    /// 一次取出 `samples` 中所有积压的数据并处理，每凑满一个 chunk 调用一次 `on_chunk`，
+    /// 返回取出的采样数
+    pub fn process<F>(&mut self, samples: &mut RingConsumer, mut on_chunk: F) -> Result<usize>
+    where
+        F: FnMut(&[i16]) -> Result<()>,
+    {
+        self.pcm.clear();
+        let count = samples.pop_into(&mut self.pcm);
+        if count == 0 {
+            return Ok(0);
+        }
+
+        self.f32_buffer.clear();
+        self.pipeline.process(&self.pcm, &mut self.f32_buffer)?;
+        self.i16_buffer.extend(
+            self.f32_buffer
+                .iter()
+                .map(|&sample| sample.clamp(-32768.0, 32767.0) as i16),
+        );
+
+        while self.i16_buffer.len() >= self.samples_per_chunk {
+            self.chunk.clear();
+            self.chunk
+                .extend(self.i16_buffer.drain(..self.samples_per_chunk));
+            on_chunk(&self.chunk)?;
+        }
+        Ok(count)
+    }
 }
 
 pub struct AudioBufferProcessor {
     recognizer: Option<AudioRecognizer>,
+    /// 音频后端名称，`None` 为系统默认后端
+    host: Option<String>,
+    /// 首选输入设备的 ID 或名称
     input_device_name: String,
-    stream: Option<cpal::Stream>,
+    input: Option<InputStreamSupervisor>,
+    input_stats: Arc<InputStats>,
     thread_handle: Option<JoinHandle<Result<AudioRecognizer>>>,
     is_speaking: Option<Arc<AtomicBool>>,
     is_finalized: Option<Arc<AtomicBool>>,
+    is_paused: Option<Arc<AtomicBool>>,
+    talk_gate: Option<Arc<TalkGate>>,
+    talk_mode: TalkMode,
+    hybrid_listen_duration: u64,
 }
 
 impl AudioBufferProcessor {
     pub fn new(recognizer: AudioRecognizer) -> Result<Self> {
-        // get default input device name
-        let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .context("Failed to get default input device")?;
-        let input_device_name = device.name().context("Failed to get device name")?;
-
-        info!("default input device name: {}", &input_device_name);
-
-        let is_speaking = Arc::clone(&recognizer.is_speaking);
-        let is_finalized = Arc::clone(&recognizer.is_finalized);
-
-        Ok(Self {
-            recognizer: Some(recognizer),
-            input_device_name,
-            stream: None,
-            thread_handle: None,
-            is_speaking: Some(is_speaking),
-            is_finalized: Some(is_finalized),
-        })
+        Self::new_with_host(recognizer, None, None)
     }
 
     pub fn new_with_input_device_name(
         recognizer: AudioRecognizer,
         input_device_name: String,
     ) -> Result<Self> {
+        Self::new_with_host(recognizer, None, Some(input_device_name))
+    }
+
+    /// 指定音频后端和输入设备 (ID 或名称)，不指定设备时使用该后端的默认输入设备
+    pub fn new_with_host(
+        recognizer: AudioRecognizer,
+        host: Option<String>,
+        input_device_name: Option<String>,
+    ) -> Result<Self> {
+        let input_device_name = match input_device_name {
+            Some(name) => name,
+            None => {
+                // get default input device name
+                let device = get_host(host.as_deref())?
+                    .default_input_device()
+                    .context("Failed to get default input device")?;
+                let name = device.name().context("Failed to get device name")?;
+                info!("default input device name: {}", &name);
+                name
+            }
+        };
+
         let is_speaking = Arc::clone(&recognizer.is_speaking);
         let is_finalized = Arc::clone(&recognizer.is_finalized);
+        let is_paused = Arc::clone(&recognizer.is_paused);
+        let talk_gate = Arc::clone(&recognizer.talk_gate);
+        let talk_mode = recognizer.config.talk_mode;
+        let hybrid_listen_duration = recognizer.config.hybrid_listen_duration;
 
         Ok(Self {
             recognizer: Some(recognizer),
+            host,
             input_device_name,
-            stream: None,
+            input: None,
+            input_stats: Arc::new(InputStats::default()),
             thread_handle: None,
             is_speaking: Some(is_speaking),
             is_finalized: Some(is_finalized),
+            is_paused: Some(is_paused),
+            talk_gate: Some(talk_gate),
+            talk_mode,
+            hybrid_listen_duration,
         })
     }
 
+    /// 输入采样的统计，包括因处理不及时而丢弃的采样数
+    pub fn input_stats(&self) -> Arc<InputStats> {
+        Arc::clone(&self.input_stats)
+    }
+
     pub fn get_speech_controller(&self) -> AudioSpeechController {
         AudioSpeechController {
             is_speaking: self.is_speaking.clone(),
             is_finalized: self.is_finalized.clone(),
+            is_paused: self.is_paused.clone(),
+            talk_gate: self.talk_gate.clone(),
+            talk_mode: self.talk_mode,
+            hybrid_listen_duration: self.hybrid_listen_duration,
         }
     }
 
@@ -321,290 +904,74 @@ impl AudioBufferProcessor {
             .take()
             .ok_or_else(|| anyhow::anyhow!("Recognizer is already running or missing"))?;
 
-        let host = cpal::default_host();
-        let mut target_device = None;
-        for device in host.input_devices()? {
-            if let std::result::Result::Ok(name) = device.name() {
-                if name == self.input_device_name {
-                    target_device = Some(device);
-                    break;
-                }
-            }
-        }
-        let device = target_device
-            .or_else(|| host.default_input_device())
-            .context("Failed to find input device")?;
-
-        let config = device
-            .default_input_config()
-            .context("Failed to get default input config")?;
-        let sample_rate = config.sample_rate().0;
-        let channels = config.channels();
-        let sample_format = config.sample_format();
-
-        let (tx, rx) = std::sync::mpsc::sync_channel::<Vec<f32>>(100);
-
-        let error_callback = |err| log::error!("an error occurred on stream: {}", err);
-
-        let tx_clone = tx.clone();
-
-        fn process_to_mono_f32<T: Copy>(
-            data: &[T],
-            channels: u16,
-            to_f32: impl Fn(T) -> f32,
-        ) -> Vec<f32> {
-            let mut output = Vec::with_capacity(data.len() / channels as usize);
-            for frame in data.chunks(channels as usize) {
-                let mut sum = 0.0;
-                for &sample in frame {
-                    sum += to_f32(sample);
-                }
-                output.push(sum / channels as f32);
+        // 在启动线程前构建管线 (包括 VAD)，模型缺失等错误直接返回给调用方
+        let pipeline = match recognizer.create_pipeline() {
+            Ok(pipeline) => pipeline,
+            Err(e) => {
+                self.recognizer = Some(recognizer);
+                return Err(e);
             }
-            output
-        }
+        };
 
-        let stream = match sample_format {
-            cpal::SampleFormat::F32 => device.build_input_stream(
-                &config.into(),
-                move |data: &[f32], _: &_| {
-                    if let Err(std::sync::mpsc::TrySendError::Full(_)) =
-                        tx_clone.try_send(process_to_mono_f32(data, channels, |x| x * 32768.0))
-                    {
-                        log::warn!("Audio processing is too slow, dropping frames");
-                    }
-                },
-                error_callback,
-                None,
-            )?,
-            cpal::SampleFormat::I16 => device.build_input_stream(
-                &config.into(),
-                move |data: &[i16], _: &_| {
-                    if let Err(std::sync::mpsc::TrySendError::Full(_)) =
-                        tx_clone.try_send(process_to_mono_f32(data, channels, |x| x as f32))
-                    {
-                        log::warn!("Audio processing is too slow, dropping frames");
-                    }
-                },
-                error_callback,
-                None,
-            )?,
-            cpal::SampleFormat::U16 => device.build_input_stream(
-                &config.into(),
-                move |data: &[u16], _: &_| {
-                    if let Err(std::sync::mpsc::TrySendError::Full(_)) = tx_clone
-                        .try_send(process_to_mono_f32(data, channels, |x| x as f32 - 32768.0))
-                    {
-                        log::warn!("Audio processing is too slow, dropping frames");
-                    }
-                },
-                error_callback,
-                None,
-            )?,
-            _ => {
+        let (tx, rx) = std::sync::mpsc::channel::<InputMessage>();
+        let options = InputOptions {
+            host: self.host.clone(),
+            channel_mix: recognizer.config.channel_mix.clone(),
+            buffer_duration: recognizer.config.input_buffer_duration,
+            stats: Arc::clone(&self.input_stats),
+        };
+        let input = match InputStreamSupervisor::spawn(
+            self.input_device_name.clone(),
+            options,
+            tx,
+            recognizer.events.clone(),
+        ) {
+            Ok(input) => input,
+            Err(e) => {
                 self.recognizer = Some(recognizer);
-                return Err(anyhow::anyhow!(
-                    "Unsupported sample format {:?}",
-                    sample_format
-                ));
+                return Err(e);
             }
         };
+        self.input = Some(input);
 
-        stream.play()?;
-        self.stream = Some(stream);
-
-        let chunk_time = recognizer.config.chunk_time;
-        let samples_per_chunk = (chunk_time * VOSK_SAMPLE_RATE) as usize;
-        let enable_denoise = recognizer.config.enable_denoise;
+        let mut processor = ChunkProcessor::new(pipeline, recognizer.config.samples_per_chunk());
+        let mut stats_monitor = InputStatsMonitor::new(Arc::clone(&self.input_stats));
 
         let handle = std::thread::spawn(move || -> Result<AudioRecognizer> {
-            use nnnoiseless::DenoiseState;
-            use rubato::{
-                Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType,
-                WindowFunction,
-            };
-
-            let mut vad = Vad::new_with_rate_and_mode(SampleRate::Rate16kHz, VadMode::Aggressive);
-            let mut i16_buffer: Vec<i16> = Vec::new();
-
-            if enable_denoise {
-                debug!("Denoise enabled (Device SR -> 48k -> Denoise -> 16k)");
-                let mut denoiser = DenoiseState::new();
-                const FRAME_SIZE: usize = 480;
-                let mut f32_48k_buffer: Vec<f32> = Vec::new();
-
-                let mut resampler_out = SincFixedIn::<f32>::new(
-                    16000.0 / 48000.0,
-                    2.0,
-                    SincInterpolationParameters {
-                        sinc_len: 256,
-                        f_cutoff: 0.95,
-                        interpolation: SincInterpolationType::Linear,
-                        oversampling_factor: 256,
-                        window: WindowFunction::BlackmanHarris2,
-                    },
-                    FRAME_SIZE,
-                    1,
-                )
-                .map_err(|e| anyhow::anyhow!("Failed to create out resampler: {}", e))?;
-
-                if sample_rate == 48000 {
-                    for pcm in rx.iter() {
-                        f32_48k_buffer.extend(pcm);
-
-                        while f32_48k_buffer.len() >= FRAME_SIZE {
-                            let chunk: Vec<f32> = f32_48k_buffer.drain(..FRAME_SIZE).collect();
-                            let mut in_frame = [0.0f32; FRAME_SIZE];
-                            in_frame.copy_from_slice(&chunk);
-
-                            let mut out_frame = [0.0f32; FRAME_SIZE];
-                            let _ = denoiser.process_frame(&mut out_frame, &in_frame);
-
-                            let waves_in = vec![out_frame.to_vec()];
-                            let resampled = resampler_out
-                                .process(&waves_in, None)
-                                .map_err(|e| anyhow::anyhow!("Resampling out error: {}", e))?;
-
-                            for &sample in &resampled[0] {
-                                i16_buffer.push((sample as f32).clamp(-32768.0, 32767.0) as i16);
-                            }
-
-                            while i16_buffer.len() >= samples_per_chunk {
-                                let chunk: Vec<i16> =
-                                    i16_buffer.drain(..samples_per_chunk).collect();
-                                recognizer.detect_speech(&chunk, &mut vad)?;
-                                let _ = recognizer.process_audio_chunk(&chunk)?;
-                                if let Some(result) = recognizer.finalize()? {
-                                    on_result(result);
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    let in_chunk_size = 1024;
-                    let mut resampler_in = SincFixedIn::<f32>::new(
-                        48000.0 / sample_rate as f64,
-                        2.0,
-                        SincInterpolationParameters {
-                            sinc_len: 256,
-                            f_cutoff: 0.95,
-                            interpolation: SincInterpolationType::Linear,
-                            oversampling_factor: 256,
-                            window: WindowFunction::BlackmanHarris2,
-                        },
-                        in_chunk_size,
-                        1,
-                    )
-                    .map_err(|e| anyhow::anyhow!("Failed to create in resampler: {}", e))?;
-
-                    let mut f32_in_buffer: Vec<f32> = Vec::new();
-
-                    for pcm in rx.iter() {
-                        f32_in_buffer.extend(pcm);
-
-                        while f32_in_buffer.len() >= in_chunk_size {
-                            let input_chunk: Vec<f32> =
-                                f32_in_buffer.drain(..in_chunk_size).collect();
-                            let waves_in = vec![input_chunk];
-                            let resampled_in = resampler_in
-                                .process(&waves_in, None)
-                                .map_err(|e| anyhow::anyhow!("Resampling in error: {}", e))?;
-
-                            f32_48k_buffer.extend(&resampled_in[0]);
-
-                            while f32_48k_buffer.len() >= FRAME_SIZE {
-                                let chunk: Vec<f32> = f32_48k_buffer.drain(..FRAME_SIZE).collect();
-                                let mut in_frame = [0.0f32; FRAME_SIZE];
-                                in_frame.copy_from_slice(&chunk);
-
-                                let mut out_frame = [0.0f32; FRAME_SIZE];
-                                let _ = denoiser.process_frame(&mut out_frame, &in_frame);
-
-                                let waves_out = vec![out_frame.to_vec()];
-                                let resampled_out = resampler_out
-                                    .process(&waves_out, None)
-                                    .map_err(|e| anyhow::anyhow!("Resampling out error: {}", e))?;
-
-                                for &sample in &resampled_out[0] {
-                                    i16_buffer
-                                        .push((sample as f32).clamp(-32768.0, 32767.0) as i16);
-                                }
-
-                                while i16_buffer.len() >= samples_per_chunk {
-                                    let chunk: Vec<i16> =
-                                        i16_buffer.drain(..samples_per_chunk).collect();
-                                    recognizer.detect_speech(&chunk, &mut vad)?;
-                                    let _ = recognizer.process_audio_chunk(&chunk)?;
-                                    if let Some(result) = recognizer.finalize()? {
-                                        on_result(result);
-                                    }
-                                }
-                            }
-                        }
+            let mut source: Option<RingConsumer> = None;
+
+            loop {
+                match rx.try_recv() {
+                    Ok(InputMessage::Opened {
+                        sample_rate,
+                        samples,
+                    }) => {
+                        // 设备 (重新) 打开后采样率可能变化，丢弃旧设备残留的数据
+                        processor.set_sample_rate(sample_rate)?;
+                        source = Some(samples);
+                        continue;
                     }
+                    Err(TryRecvError::Disconnected) => break,
+                    Err(TryRecvError::Empty) => {}
                 }
-            } else {
-                debug!("Denoise disabled (Device SR -> 16k)");
-                // Fast path: No denoise, resample directly to 16kHz
-                if sample_rate == 16000 {
-                    for pcm in rx.iter() {
-                        for &sample in &pcm {
-                            i16_buffer.push((sample as f32).clamp(-32768.0, 32767.0) as i16);
-                        }
-
-                        while i16_buffer.len() >= samples_per_chunk {
-                            let chunk: Vec<i16> = i16_buffer.drain(..samples_per_chunk).collect();
-                            recognizer.detect_speech(&chunk, &mut vad)?;
-                            let _ = recognizer.process_audio_chunk(&chunk)?;
-                            if let Some(result) = recognizer.finalize()? {
-                                on_result(result);
-                            }
-                        }
-                    }
-                } else {
-                    let chunk_size = 1024;
-                    let mut resampler = SincFixedIn::<f32>::new(
-                        16000.0 / sample_rate as f64,
-                        2.0,
-                        SincInterpolationParameters {
-                            sinc_len: 256,
-                            f_cutoff: 0.95,
-                            interpolation: SincInterpolationType::Linear,
-                            oversampling_factor: 256,
-                            window: WindowFunction::BlackmanHarris2,
-                        },
-                        chunk_size,
-                        1,
-                    )
-                    .map_err(|e| anyhow::anyhow!("Failed to create resampler: {}", e))?;
-
-                    let mut f32_buffer: Vec<f32> = Vec::new();
-
-                    for pcm in rx.iter() {
-                        f32_buffer.extend(pcm);
-
-                        while f32_buffer.len() >= chunk_size {
-                            let input_chunk: Vec<f32> = f32_buffer.drain(..chunk_size).collect();
-                            let waves_in = vec![input_chunk];
-                            let resampled = resampler
-                                .process(&waves_in, None)
-                                .map_err(|e| anyhow::anyhow!("Resampling error: {}", e))?;
-
-                            for &sample in &resampled[0] {
-                                i16_buffer.push((sample as f32).clamp(-32768.0, 32767.0) as i16);
-                            }
-
-                            while i16_buffer.len() >= samples_per_chunk {
-                                let chunk: Vec<i16> =
-                                    i16_buffer.drain(..samples_per_chunk).collect();
-                                recognizer.detect_speech(&chunk, &mut vad)?;
-                                let _ = recognizer.process_audio_chunk(&chunk)?;
-                                if let Some(result) = recognizer.finalize()? {
-                                    on_result(result);
-                                }
-                            }
-                        }
+                let Some(samples) = source.as_mut() else {
+                    std::thread::sleep(PROCESS_POLL_INTERVAL);
+                    continue;
+                };
+
+                stats_monitor.poll(&recognizer.events);
+
+                // 一次取出所有积压的数据，处理慢时可以追上进度
+                let count = processor.process(samples, |chunk| {
+                    recognizer.detect_speech(chunk);
+                    let _ = recognizer.process_audio_chunk(chunk)?;
+                    if let Some(result) = recognizer.finalize()? {
+                        on_result(result);
                     }
+                    Ok(())
+                })?;
+                if count == 0 {
+                    std::thread::sleep(PROCESS_POLL_INTERVAL);
                 }
             }
             Ok(recognizer)
@@ -615,11 +982,12 @@ impl AudioBufferProcessor {
     }
 
     pub fn is_start(&self) -> bool {
-        self.stream.is_some()
+        self.input.is_some()
     }
 
     pub fn stop(&mut self) -> Result<()> {
-        self.stream = None;
+        // 停止输入流后处理线程会在取完剩余数据后退出
+        self.input = None;
         if let Some(handle) = self.thread_handle.take() {
             match handle.join() {
                 Ok(Ok(mut r)) => {
@@ -639,3 +1007,28 @@ impl Drop for AudioBufferProcessor {
         let _ = self.stop();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_filter_drops_only_empty_results() {
+        let config = AudioRecognizerConfig::default();
+        assert!(!config.is_long_enough("", 500));
+        assert!(!config.is_long_enough("  ", 500));
+        assert!(config.is_long_enough("上", 0));
+    }
+
+    #[test]
+    fn filter_checks_duration_and_chars() {
+        let config = AudioRecognizerConfig {
+            min_utterance_duration: 200,
+            min_utterance_chars: 2,
+            ..Default::default()
+        };
+        assert!(config.is_long_enough("增 援", 200));
+        assert!(!config.is_long_enough("增援", 199));
+        assert!(!config.is_long_enough("增 ", 500));
+    }
+}
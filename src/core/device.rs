@@ -0,0 +1,262 @@
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait};
+use log::warn;
+use serde::Serialize;
+
+/// 输入设备信息
+#[derive(Debug, Clone, Serialize)]
+pub struct InputDeviceInfo {
+    /// 稳定的设备 ID，格式为 `<host>:<设备名>`，同名设备依次追加 `#2`、`#3`…
+    ///
+    /// 后缀取决于后端枚举设备的顺序，插拔或重启后同名设备的编号可能互换，
+    /// 因此同名设备的 ID 只用于展示，见 [`InputDeviceInfo::ambiguous`]。
+    pub id: String,
+    pub name: String,
+    /// 是否存在同名设备，此时无法稳定地区分它们，ID 不能保存到配置，也不会被解析
+    pub ambiguous: bool,
+    /// 是否为系统默认输入设备
+    pub is_default: bool,
+    /// 设备支持的输入配置
+    pub configs: Vec<InputConfigInfo>,
+}
+
+/// 输入设备支持的一种配置
+#[derive(Debug, Clone, Serialize)]
+pub struct InputConfigInfo {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+}
+
+/// 当前平台可用的音频后端名称，如 "ALSA"、"JACK"、"WASAPI"、"ASIO"
+pub fn list_hosts() -> Vec<&'static str> {
+    cpal::available_hosts()
+        .into_iter()
+        .map(|id| id.name())
+        .collect()
+}
+
+/// 按名称 (不区分大小写) 获取音频后端，不指定时使用系统默认后端
+pub fn get_host(name: Option<&str>) -> Result<cpal::Host> {
+    let Some(name) = name.filter(|x| !x.is_empty()) else {
+        return Ok(cpal::default_host());
+    };
+    let id = cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .with_context(|| {
+            format!(
+                "Audio host '{}' is not available, available hosts: {:?}",
+                name,
+                list_hosts()
+            )
+        })?;
+    cpal::host_from_id(id).with_context(|| format!("Failed to initialize audio host '{}'", name))
+}
+
+/// 列出所有输入设备，名称匹配 `exclude` 的设备不会出现在结果中
+pub fn list_input_devices(host: &cpal::Host, exclude: &[String]) -> Result<Vec<InputDeviceInfo>> {
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+
+    let all = enumerate_input_devices(host)?;
+    let names = all
+        .iter()
+        .map(|(_, name, _)| name.clone())
+        .collect::<Vec<_>>();
+    let mut devices = Vec::new();
+    for (id, name, device) in all {
+        if is_excluded(&name, exclude) {
+            continue;
+        }
+        let configs = match device.supported_input_configs() {
+            Ok(configs) => configs
+                .map(|c| InputConfigInfo {
+                    channels: c.channels(),
+                    min_sample_rate: c.min_sample_rate().0,
+                    max_sample_rate: c.max_sample_rate().0,
+                    sample_format: c.sample_format().to_string(),
+                })
+                .collect(),
+            Err(e) => {
+                warn!("Failed to get supported input configs of '{}': {}", name, e);
+                Vec::new()
+            }
+        };
+        devices.push(InputDeviceInfo {
+            is_default: default_name.as_deref() == Some(name.as_str()),
+            ambiguous: is_ambiguous(&names, &name),
+            id,
+            name,
+            configs,
+        });
+    }
+    Ok(devices)
+}
+
+/// 按设备 ID 或名称查找输入设备，ID 优先
+///
+/// 有同名设备时无法确定指的是哪一个，返回 `None`。
+pub fn find_input_device(host: &cpal::Host, id_or_name: &str) -> Option<cpal::Device> {
+    find_device(enumerate_input_devices(host).ok()?, id_or_name)
+}
+
+/// 按设备 ID 或名称查找输出设备，ID 优先
+///
+/// 输出设备 ID 的格式与输入设备相同。
+pub fn find_output_device(host: &cpal::Host, id_or_name: &str) -> Option<cpal::Device> {
+    find_device(enumerate_output_devices(host).ok()?, id_or_name)
+}
+
+/// 所有输出设备的 ID，用于提示用户
+pub fn list_output_device_ids(host: &cpal::Host) -> Vec<String> {
+    enumerate_output_devices(host)
+        .map(|devices| devices.into_iter().map(|(id, _, _)| id).collect())
+        .unwrap_or_default()
+}
+
+fn find_device(
+    devices: Vec<(String, String, cpal::Device)>,
+    id_or_name: &str,
+) -> Option<cpal::Device> {
+    let index = devices
+        .iter()
+        .position(|(id, _, _)| id == id_or_name)
+        .or_else(|| devices.iter().position(|(_, name, _)| name == id_or_name))?;
+
+    let names = devices
+        .iter()
+        .map(|(_, name, _)| name.clone())
+        .collect::<Vec<_>>();
+    if is_ambiguous(&names, &names[index]) {
+        warn!(
+            "Multiple devices are named '{}', '{}' cannot be resolved reliably, \
+             use the default device or disconnect the others",
+            names[index], id_or_name
+        );
+        return None;
+    }
+    devices.into_iter().nth(index).map(|(_, _, device)| device)
+}
+
+/// 自动选择输入设备，返回设备 ID
+///
+/// 优先使用系统默认设备，默认设备被排除时使用第一个未被排除的设备，有同名设备的不会被选中。
+pub fn select_input_device(host: &cpal::Host, exclude: &[String]) -> Result<String> {
+    let mut devices = list_input_devices(host, exclude)?;
+    devices.retain(|d| !d.ambiguous);
+    devices
+        .iter()
+        .find(|d| d.is_default)
+        .or_else(|| devices.first())
+        .map(|d| d.id.clone())
+        .context("No available input device found")
+}
+
+/// 设备名是否包含 `exclude` 中的任意一项 (不区分大小写)
+pub fn is_excluded(name: &str, exclude: &[String]) -> bool {
+    let name = name.to_lowercase();
+    exclude
+        .iter()
+        .filter(|pattern| !pattern.is_empty())
+        .any(|pattern| name.contains(&pattern.to_lowercase()))
+}
+
+/// 枚举输入设备，返回 (ID, 名称, 设备)
+fn enumerate_input_devices(host: &cpal::Host) -> Result<Vec<(String, String, cpal::Device)>> {
+    let devices = host
+        .input_devices()
+        .context("Failed to enumerate input devices")?;
+    Ok(identify_devices(host, devices))
+}
+
+/// 枚举输出设备，返回 (ID, 名称, 设备)
+fn enumerate_output_devices(host: &cpal::Host) -> Result<Vec<(String, String, cpal::Device)>> {
+    let devices = host
+        .output_devices()
+        .context("Failed to enumerate output devices")?;
+    Ok(identify_devices(host, devices))
+}
+
+/// 为设备生成 ID
+fn identify_devices(
+    host: &cpal::Host,
+    iter: impl Iterator<Item = cpal::Device>,
+) -> Vec<(String, String, cpal::Device)> {
+    let host_name = host.id().name().to_lowercase();
+    let (names, devices): (Vec<String>, Vec<cpal::Device>) = iter
+        .filter_map(|device| Some((device.name().ok()?, device)))
+        .unzip();
+    assign_ids(&host_name, &names)
+        .into_iter()
+        .zip(names)
+        .zip(devices)
+        .map(|((id, name), device)| (id, name, device))
+        .collect()
+}
+
+/// 按设备名生成 ID，与 `names` 一一对应
+///
+/// 同名设备按出现顺序追加 `#2`、`#3`… 后缀，顺序由后端决定，不保证跨重启稳定，
+/// 因此这些设备被标记为有歧义，见 [`is_ambiguous`]。
+fn assign_ids(host_name: &str, names: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let duplicates = names[..index].iter().filter(|n| *n == name).count();
+            if duplicates == 0 {
+                format!("{}:{}", host_name, name)
+            } else {
+                format!("{}:{}#{}", host_name, name, duplicates + 1)
+            }
+        })
+        .collect()
+}
+
+/// `name` 是否有同名设备
+fn is_ambiguous(names: &[String], name: &str) -> bool {
+    names.iter().filter(|n| *n == name).count() > 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn unique_names_get_plain_ids() {
+        let names = names(&["Mic", "Headset"]);
+        assert_eq!(assign_ids("alsa", &names), ["alsa:Mic", "alsa:Headset"]);
+        assert!(!is_ambiguous(&names, "Mic"));
+        assert!(!is_ambiguous(&names, "Headset"));
+    }
+
+    #[test]
+    fn duplicate_names_get_suffix_and_are_ambiguous() {
+        let names = names(&["USB Audio", "Mic", "USB Audio", "USB Audio"]);
+        assert_eq!(
+            assign_ids("wasapi", &names),
+            [
+                "wasapi:USB Audio",
+                "wasapi:Mic",
+                "wasapi:USB Audio#2",
+                "wasapi:USB Audio#3"
+            ]
+        );
+        assert!(is_ambiguous(&names, "USB Audio"));
+        assert!(!is_ambiguous(&names, "Mic"));
+    }
+
+    #[test]
+    fn ids_do_not_depend_on_other_devices() {
+        // 唯一的设备在其他设备插拔前后 ID 不变
+        let before = assign_ids("alsa", &names(&["Mic", "Headset"]));
+        let after = assign_ids("alsa", &names(&["Webcam", "Headset", "Mic"]));
+        assert_eq!(before[0], after[2]);
+        assert_eq!(before[1], after[1]);
+    }
+}
@@ -0,0 +1,72 @@
+use log::debug;
+use std::sync::{Arc, RwLock};
+
+use crate::core::keypress::LocalKey;
+
+/// 引擎运行过程中产生的事件，供上层（如桌面端）展示状态
+#[derive(Debug, Clone)]
+pub enum HellcallEvent {
+    /// 待执行队列已满，最早入队的战备被挤出
+    StratagemOverwritten { keys: Vec<LocalKey> },
+    /// 待执行的战备超过存活时间，已被丢弃
+    StratagemExpired { keys: Vec<LocalKey> },
+    /// 通过 TOGGLE 键启用或暂停了 hellcall
+    EnabledChanged { enabled: bool },
+    /// 单次语音超过最大时长被强制结束识别，`duration` 单位为毫秒
+    UtteranceTruncated { duration: u64 },
+    /// 每个音频 chunk 经过增益后的输入电平，`rms`、`peak` 单位为 dBFS，`gain` 单位为 dB
+    InputLevel { rms: f32, peak: f32, gain: f32 },
+    /// 输入设备的原始采样出现削波，`samples` 为上次报告以来削波的采样数，与增益无关，
+    /// 说明需要调低系统中的麦克风音量
+    InputClipped { samples: u64 },
+    /// 处理线程跟不上输入，缓冲区溢出丢弃了采样，`samples` 为上次报告以来丢弃的采样数
+    InputOverflow { samples: u64 },
+    /// 打开了音频输入设备，`fallback` 表示首选设备不可用，使用的是默认设备
+    InputDeviceOpened { device: String, fallback: bool },
+    /// 音频输入流出错或长时间没有数据，正在尝试恢复
+    InputStreamLost { device: String, error: String },
+    /// 打开音频输入设备失败，`retry_in` 毫秒后重试
+    InputDeviceUnavailable { error: String, retry_in: u64 },
+    /// 音效文件打开或解码失败，已跳过该音效
+    AudioPlaybackFailed { path: String, error: String },
+}
+
+type Listener = Arc<dyn Fn(HellcallEvent) + Send + Sync>;
+
+/// 事件分发器
+///
+/// clone 出来的实例共享同一个监听器，各模块持有一份 clone 即可向外发送事件。
+/// 未设置监听器时 `emit` 为空操作。
+#[derive(Clone, Default)]
+pub struct EventEmitter {
+    listener: Arc<RwLock<Option<Listener>>>,
+}
+
+impl EventEmitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置事件监听器，会替换掉已有的监听器
+    pub fn set_listener<F>(&self, listener: F)
+    where
+        F: Fn(HellcallEvent) + Send + Sync + 'static,
+    {
+        *self.listener.write().unwrap() = Some(Arc::new(listener));
+    }
+
+    pub fn clear_listener(&self) {
+        *self.listener.write().unwrap() = None;
+    }
+
+    /// 发送事件
+    ///
+    /// 可能在系统按键钩子中调用，因此使用 try_read，绝不阻塞调用方。
+    pub fn emit(&self, event: HellcallEvent) {
+        debug!("event: {:?}", event);
+        let listener = self.listener.try_read().ok().and_then(|l| l.clone());
+        if let Some(listener) = listener {
+            listener(event);
+        }
+    }
+}
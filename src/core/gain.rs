@@ -0,0 +1,292 @@
+use anyhow::Result;
+
+use crate::core::event::{EventEmitter, HellcallEvent};
+use crate::core::pipeline::AudioStage;
+
+/// 低于该电平 (dBFS) 视为静音，AGC 不会为了静音拉高增益
+const AGC_NOISE_GATE: f32 = -50.0;
+/// AGC 每个 chunk 向目标增益靠近的比例
+const AGC_SMOOTHING: f32 = 0.2;
+/// AGC 允许的最低增益 (dB)
+const AGC_MIN_GAIN: f32 = -20.0;
+
+/// 一个 chunk 的输入电平
+#[derive(Debug, Clone, Copy)]
+pub struct InputLevel {
+    /// 均方根电平 (dBFS)
+    pub rms: f32,
+    /// 峰值电平 (dBFS)
+    pub peak: f32,
+    /// 增益后削波的采样数，AGC 据此立即降低增益
+    pub clipped: usize,
+    /// 实际使用的增益 (dB)，包含 AGC 的调整
+    pub gain: f32,
+}
+
+/// 输入增益与自动增益控制 (AGC)
+///
+/// 在 VAD 之前处理 16kHz 音频，耳麦音量过小时提高识别率，同时统计增益后的电平。
+/// 设备本身的削波在输入流回调中统计，见 `InputStats::clipped_samples`。
+pub struct InputGain {
+    /// 固定增益 (dB)
+    gain: f32,
+    /// AGC 目标电平 (dBFS)，`None` 表示不启用 AGC
+    agc_target: Option<f32>,
+    agc_max_gain: f32,
+    /// AGC 当前的额外增益 (dB)
+    agc_gain: f32,
+}
+
+impl InputGain {
+    pub fn new(gain: f32, agc_target: Option<f32>, agc_max_gain: f32) -> Self {
+        Self {
+            gain,
+            agc_target,
+            agc_max_gain,
+            agc_gain: 0.0,
+        }
+    }
+
+    /// 对一个 chunk 应用增益，返回处理后的电平
+    ///
+    /// 采样是 i16 量级的 f32，处理后限制在 i16 范围内。
+    pub fn process(&mut self, chunk: &mut [f32]) -> InputLevel {
+        let gain = self.gain + self.agc_gain;
+        let factor = db_to_amplitude(gain);
+
+        let mut clipped = 0;
+        let mut peak = 0.0f32;
+        let mut sum = 0.0f64;
+        for sample in chunk.iter_mut() {
+            let value = *sample * factor;
+            if value >= i16::MAX as f32 || value <= i16::MIN as f32 {
+                clipped += 1;
+            }
+            let value = value.clamp(i16::MIN as f32, i16::MAX as f32);
+            peak = peak.max(value.abs());
+            sum += (value as f64) * (value as f64);
+            *sample = value;
+        }
+
+        let rms = if chunk.is_empty() {
+            0.0
+        } else {
+            (sum / chunk.len() as f64).sqrt() as f32
+        };
+        let level = InputLevel {
+            rms: amplitude_to_db(rms),
+            peak: amplitude_to_db(peak),
+            clipped,
+            gain,
+        };
+
+        if let Some(target) = self.agc_target {
+            self.update_agc(target, &level);
+        }
+
+        level
+    }
+
+    /// 应用增益并将电平作为事件发送出去
+    pub fn apply(&mut self, chunk: &mut [f32], events: &EventEmitter) {
+        let level = self.process(chunk);
+        events.emit(HellcallEvent::InputLevel {
+            rms: level.rms,
+            peak: level.peak,
+            gain: level.gain,
+        });
+    }
+
+    fn update_agc(&mut self, target: f32, level: &InputLevel) {
+        if level.rms < AGC_NOISE_GATE {
+            return;
+        }
+        // 削波时立即降低增益，否则平滑地向目标电平靠近
+        let desired = if level.clipped > 0 {
+            self.agc_gain - 6.0
+        } else {
+            self.agc_gain + (target - level.rms)
+        };
+        let desired = desired.clamp(AGC_MIN_GAIN, self.agc_max_gain);
+        if level.clipped > 0 {
+            self.agc_gain = desired;
+        } else {
+            self.agc_gain += (desired - self.agc_gain) * AGC_SMOOTHING;
+        }
+    }
+}
+
+/// 管线中的增益环节
+///
+/// 按识别的 chunk 大小分块处理，AGC 每块调整一次，并发送一次电平事件。
+pub struct GainStage {
+    gain: InputGain,
+    block_samples: usize,
+    buffer: Vec<f32>,
+    events: EventEmitter,
+}
+
+impl GainStage {
+    pub fn new(gain: InputGain, block_samples: usize, events: EventEmitter) -> Self {
+        Self {
+            gain,
+            block_samples: block_samples.max(1),
+            buffer: Vec::with_capacity(block_samples * 2),
+            events,
+        }
+    }
+}
+
+impl AudioStage for GainStage {
+    fn name(&self) -> &'static str {
+        "gain"
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        self.buffer.extend_from_slice(input);
+        let mut consumed = 0;
+        while self.buffer.len() - consumed >= self.block_samples {
+            let block = &mut self.buffer[consumed..consumed + self.block_samples];
+            self.gain.apply(block, &self.events);
+            output.extend_from_slice(block);
+            consumed += self.block_samples;
+        }
+        self.buffer.drain(..consumed);
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        if !self.buffer.is_empty() {
+            self.gain.apply(&mut self.buffer, &self.events);
+            output.extend_from_slice(&self.buffer);
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+
+    /// 只丢弃缓存的音频，AGC 的增益保留
+    fn reset(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// 将 i16 幅度转换为 dBFS
+fn amplitude_to_db(amplitude: f32) -> f32 {
+    20.0 * (amplitude.max(1.0) / i16::MAX as f32).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 振幅为 `amplitude` 的方波，RMS 与峰值相同
+    fn square(amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| if i % 2 == 0 { amplitude } else { -amplitude })
+            .collect()
+    }
+
+    #[test]
+    fn fixed_gain_scales_samples() {
+        let mut gain = InputGain::new(6.0, None, 0.0);
+        let mut chunk = square(1000.0, 160);
+        let level = gain.process(&mut chunk);
+        assert!((chunk[0] - 1000.0 * db_to_amplitude(6.0)).abs() < 0.01);
+        assert_eq!(level.gain, 6.0);
+        assert_eq!(level.clipped, 0);
+    }
+
+    #[test]
+    fn clipping_is_counted_and_clamped() {
+        let mut gain = InputGain::new(12.0, None, 0.0);
+        let mut chunk = square(20000.0, 160);
+        let level = gain.process(&mut chunk);
+        assert_eq!(level.clipped, 160);
+        assert!(
+            chunk
+                .iter()
+                .all(|s| (i16::MIN as f32..=i16::MAX as f32).contains(s))
+        );
+    }
+
+    #[test]
+    fn agc_converges_to_target() {
+        let target = -20.0;
+        let mut gain = InputGain::new(0.0, Some(target), 30.0);
+        let mut rms = 0.0;
+        for _ in 0..100 {
+            // -40 dBFS 的输入，需要约 20 dB 的增益
+            let mut chunk = square(i16::MAX as f32 * 0.01, 160);
+            rms = gain.process(&mut chunk).rms;
+        }
+        assert!((rms - target).abs() < 0.5, "rms {}", rms);
+    }
+
+    #[test]
+    fn agc_respects_max_gain() {
+        let mut gain = InputGain::new(0.0, Some(-10.0), 6.0);
+        let mut level = None;
+        for _ in 0..100 {
+            let mut chunk = square(i16::MAX as f32 * 0.01, 160);
+            level = Some(gain.process(&mut chunk));
+        }
+        assert!(level.unwrap().gain <= 6.0);
+    }
+
+    #[test]
+    fn agc_ignores_silence() {
+        let mut gain = InputGain::new(0.0, Some(-20.0), 30.0);
+        for _ in 0..20 {
+            let mut chunk = square(1.0, 160);
+            gain.process(&mut chunk);
+        }
+        assert_eq!(gain.agc_gain, 0.0);
+    }
+
+    #[test]
+    fn agc_backs_off_immediately_on_clipping() {
+        let mut gain = InputGain::new(0.0, Some(-3.0), 30.0);
+        gain.agc_gain = 10.0;
+        let mut chunk = square(20000.0, 160);
+        gain.process(&mut chunk);
+        assert_eq!(gain.agc_gain, 4.0);
+    }
+
+    #[test]
+    fn stage_processes_whole_blocks_and_flushes_rest() {
+        let mut stage = GainStage::new(InputGain::new(0.0, None, 0.0), 160, EventEmitter::new());
+        let mut output = Vec::new();
+        stage.process(&square(100.0, 100), &mut output).unwrap();
+        assert!(output.is_empty());
+        stage.process(&square(100.0, 100), &mut output).unwrap();
+        assert_eq!(output.len(), 160);
+        stage.flush(&mut output).unwrap();
+        assert_eq!(output.len(), 200);
+        // flush 之后没有残留
+        stage.flush(&mut output).unwrap();
+        assert_eq!(output.len(), 200);
+    }
+
+    #[test]
+    fn stage_reset_keeps_agc_gain() {
+        let mut stage = GainStage::new(
+            InputGain::new(0.0, Some(-20.0), 30.0),
+            160,
+            EventEmitter::new(),
+        );
+        let mut output = Vec::new();
+        stage
+            .process(&square(i16::MAX as f32 * 0.01, 1600), &mut output)
+            .unwrap();
+        let agc_gain = stage.gain.agc_gain;
+        assert!(agc_gain > 0.0);
+        stage.process(&square(100.0, 50), &mut output).unwrap();
+        stage.reset();
+        assert!(stage.buffer.is_empty());
+        assert_eq!(stage.gain.agc_gain, agc_gain);
+    }
+}
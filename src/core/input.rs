@@ -0,0 +1,557 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use log::{debug, error, info, warn};
+
+use crate::core::audio::ChannelMix;
+use crate::core::device::{find_input_device, get_host};
+use crate::core::event::{EventEmitter, HellcallEvent};
+use crate::core::ring::{RingConsumer, RingProducer, sample_ring};
+
+/// 检查输入流状态的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// 超过该时间没有收到音频数据，认为输入流已失效
+const STALL_TIMEOUT: Duration = Duration::from_secs(5);
+/// 使用备用设备时，检查首选设备是否重新可用的间隔
+const PREFERRED_CHECK_INTERVAL: Duration = Duration::from_secs(3);
+/// 重新打开设备失败后的首次重试间隔
+const RETRY_INITIAL_DELAY: Duration = Duration::from_millis(500);
+/// 重新打开设备的最大重试间隔
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(10);
+/// 归一化后达到该幅度的设备采样视为削波，整数格式以各自的满幅为准，见 [`clip_level`]
+const CLIP_LEVEL: f32 = 32767.0 / 32768.0;
+/// 处理线程报告输入统计变化的最小间隔
+const STATS_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 输入流发送给处理线程的消息
+pub enum InputMessage {
+    /// 输入流已 (重新) 打开，之后从 `samples` 中读取该采样率的 i16 量级单声道采样
+    Opened {
+        sample_rate: u32,
+        samples: RingConsumer,
+    },
+}
+
+/// 输入采样的统计，设备重连后继续累计
+#[derive(Debug, Default)]
+pub struct InputStats {
+    received_samples: AtomicU64,
+    dropped_samples: AtomicU64,
+    clipped_samples: AtomicU64,
+}
+
+impl InputStats {
+    /// 从设备收到的单声道采样数
+    pub fn received_samples(&self) -> u64 {
+        self.received_samples.load(Ordering::Relaxed)
+    }
+
+    /// 处理线程跟不上、缓冲区溢出而丢弃的单声道采样数
+    pub fn dropped_samples(&self) -> u64 {
+        self.dropped_samples.load(Ordering::Relaxed)
+    }
+
+    /// 设备原始采样 (混合声道和增益之前) 中削波的采样数，每个声道分别计数
+    pub fn clipped_samples(&self) -> u64 {
+        self.clipped_samples.load(Ordering::Relaxed)
+    }
+}
+
+/// 在处理线程中定期检查输入统计的变化并发送事件，输入流回调中只做计数
+pub struct InputStatsMonitor {
+    stats: Arc<InputStats>,
+    dropped_samples: u64,
+    clipped_samples: u64,
+    last_report: Instant,
+}
+
+impl InputStatsMonitor {
+    pub fn new(stats: Arc<InputStats>) -> Self {
+        Self {
+            dropped_samples: stats.dropped_samples(),
+            clipped_samples: stats.clipped_samples(),
+            stats,
+            last_report: Instant::now(),
+        }
+    }
+
+    /// 距上次报告超过 `STATS_REPORT_INTERVAL` 时报告期间的变化
+    pub fn poll(&mut self, events: &EventEmitter) {
+        if self.last_report.elapsed() < STATS_REPORT_INTERVAL {
+            return;
+        }
+        self.last_report = Instant::now();
+
+        let dropped_samples = self.stats.dropped_samples();
+        if dropped_samples > self.dropped_samples {
+            let samples = dropped_samples - self.dropped_samples;
+            warn!("Audio processing is too slow, dropped {} samples", samples);
+            events.emit(HellcallEvent::InputOverflow { samples });
+        }
+        self.dropped_samples = dropped_samples;
+
+        let clipped_samples = self.stats.clipped_samples();
+        if clipped_samples > self.clipped_samples {
+            let samples = clipped_samples - self.clipped_samples;
+            debug!("Input device clipped {} samples", samples);
+            events.emit(HellcallEvent::InputClipped { samples });
+        }
+        self.clipped_samples = clipped_samples;
+    }
+}
+
+/// 输入流守护线程
+///
+/// 负责打开输入设备，并在输入流出错、设备被拔出或长时间没有数据时自动重连：
+/// 首选设备不可用时退回默认设备，首选设备恢复后再切换回去。
+/// cpal 的 Stream 在部分平台上不是 Send 的，因此输入流只在守护线程内创建和销毁。
+pub struct InputStreamSupervisor {
+    stop_tx: mpsc::Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl InputStreamSupervisor {
+    /// 启动守护线程，等待首次打开设备的结果
+    pub fn spawn(
+        preferred_device: String,
+        options: InputOptions,
+        tx: Sender<InputMessage>,
+        events: EventEmitter,
+    ) -> Result<Self> {
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        let handle = std::thread::spawn(move || {
+            let mut worker = match InputStreamWorker::new(preferred_device, options, tx, events) {
+                Ok(worker) => worker,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let opened = worker.open();
+            let ok = opened.is_ok();
+            let _ = ready_tx.send(opened);
+            if ok {
+                worker.run(stop_rx);
+            }
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                stop_tx,
+                handle: Some(handle),
+            }),
+            Ok(Err(e)) => {
+                let _ = handle.join();
+                Err(e)
+            }
+            Err(_) => {
+                let _ = handle.join();
+                Err(anyhow::anyhow!("Input stream thread exited unexpectedly"))
+            }
+        }
+    }
+}
+
+impl Drop for InputStreamSupervisor {
+    fn drop(&mut self) {
+        let _ = self.stop_tx.send(());
+        if let Some(handle) = self.handle.take()
+            && let Err(e) = handle.join()
+        {
+            error!("Input stream thread panicked: {:?}", e);
+        }
+    }
+}
+
+/// 打开输入流的参数
+#[derive(Debug, Clone)]
+pub struct InputOptions {
+    /// 音频后端名称，不指定时使用系统默认后端
+    pub host: Option<String>,
+    pub channel_mix: ChannelMix,
+    /// 缓冲区能容纳的音频时长 (ms)
+    pub buffer_duration: u64,
+    pub stats: Arc<InputStats>,
+}
+
+struct InputStreamWorker {
+    host: cpal::Host,
+    preferred_device: String,
+    options: InputOptions,
+    tx: Sender<InputMessage>,
+    events: EventEmitter,
+    stream: Option<cpal::Stream>,
+    /// 当前打开的设备名
+    device_name: String,
+    /// 当前打开的是否为退回的默认设备
+    fallback: bool,
+    /// 输入流回调报告的错误
+    stream_error: Arc<Mutex<Option<String>>>,
+    /// 自上次检查以来是否收到过音频数据
+    received: Arc<AtomicBool>,
+    last_received: Instant,
+    /// 处理线程已退出
+    closed: bool,
+}
+
+impl InputStreamWorker {
+    fn new(
+        preferred_device: String,
+        options: InputOptions,
+        tx: Sender<InputMessage>,
+        events: EventEmitter,
+    ) -> Result<Self> {
+        Ok(Self {
+            host: get_host(options.host.as_deref())?,
+            preferred_device,
+            options,
+            tx,
+            events,
+            stream: None,
+            device_name: String::new(),
+            fallback: false,
+            stream_error: Arc::new(Mutex::new(None)),
+            received: Arc::new(AtomicBool::new(false)),
+            last_received: Instant::now(),
+            closed: false,
+        })
+    }
+
+    fn run(&mut self, stop_rx: mpsc::Receiver<()>) {
+        let mut retry_delay = RETRY_INITIAL_DELAY;
+        let mut next_retry = Instant::now();
+        let mut last_preferred_check = Instant::now();
+
+        loop {
+            match stop_rx.recv_timeout(POLL_INTERVAL) {
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+
+            if self.stream.is_some() {
+                if let Some(error) = self.check_stream() {
+                    warn!("Input stream on '{}' lost: {}", self.device_name, error);
+                    self.stream = None;
+                    self.events.emit(HellcallEvent::InputStreamLost {
+                        device: self.device_name.clone(),
+                        error,
+                    });
+                    retry_delay = RETRY_INITIAL_DELAY;
+                    next_retry = Instant::now();
+                } else if self.fallback
+                    && last_preferred_check.elapsed() >= PREFERRED_CHECK_INTERVAL
+                {
+                    last_preferred_check = Instant::now();
+                    if find_input_device(&self.host, &self.preferred_device).is_some() {
+                        info!(
+                            "Preferred input device '{}' is available again",
+                            self.preferred_device
+                        );
+                        self.stream = None;
+                        next_retry = Instant::now();
+                    }
+                }
+            }
+
+            if self.stream.is_none() && Instant::now() >= next_retry {
+                match self.open() {
+                    Ok(()) => retry_delay = RETRY_INITIAL_DELAY,
+                    Err(e) => {
+                        warn!(
+                            "Failed to reopen input device, retrying in {:?}: {:#}",
+                            retry_delay, e
+                        );
+                        self.events.emit(HellcallEvent::InputDeviceUnavailable {
+                            error: format!("{:#}", e),
+                            retry_in: retry_delay.as_millis() as u64,
+                        });
+                        next_retry = Instant::now() + retry_delay;
+                        retry_delay = (retry_delay * 2).min(RETRY_MAX_DELAY);
+                    }
+                }
+            }
+
+            if self.closed {
+                break;
+            }
+        }
+    }
+
+    /// 检查输入流是否出错或停止产出数据
+    fn check_stream(&mut self) -> Option<String> {
+        if let Some(error) = self.stream_error.lock().unwrap().take() {
+            return Some(error);
+        }
+        if self.received.swap(false, Ordering::AcqRel) {
+            self.last_received = Instant::now();
+        } else if self.last_received.elapsed() >= STALL_TIMEOUT {
+            return Some(format!("no audio data for {:?}", STALL_TIMEOUT));
+        }
+        None
+    }
+
+    /// 打开首选设备，不可用时退回默认设备
+    fn open(&mut self) -> Result<()> {
+        self.stream = None;
+
+        let (device, fallback) = match find_input_device(&self.host, &self.preferred_device) {
+            Some(device) => (device, false),
+            None => {
+                let device = self
+                    .host
+                    .default_input_device()
+                    .context("Failed to find input device")?;
+                warn!(
+                    "Input device '{}' not found, falling back to the default device",
+                    self.preferred_device
+                );
+                (device, true)
+            }
+        };
+        let device_name = device.name().unwrap_or_default();
+
+        let mut last_error = None;
+        for config in input_config_candidates(&device) {
+            let channel_weights = match self.options.channel_mix.weights(config.channels() as usize)
+            {
+                Ok(weights) => weights,
+                Err(e) => {
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+
+            let sample_rate = config.sample_rate().0;
+            let capacity = (sample_rate as u64 * self.options.buffer_duration / 1000) as usize;
+            let (producer, consumer) = sample_ring(capacity);
+
+            *self.stream_error.lock().unwrap() = None;
+            let callback = StreamCallback {
+                channel_weights,
+                clip_level: clip_level(config.sample_format()),
+                producer,
+                stats: Arc::clone(&self.options.stats),
+                received: Arc::clone(&self.received),
+                stream_error: Arc::clone(&self.stream_error),
+            };
+            let stream = match build_input_stream(&device, &config, callback) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    debug!("Failed to open input stream with {:?}: {}", config, e);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+
+            let opened = InputMessage::Opened {
+                sample_rate,
+                samples: consumer,
+            };
+            if self.tx.send(opened).is_err() {
+                self.closed = true;
+                return Err(anyhow::anyhow!("Audio processing thread has exited"));
+            }
+            stream.play()?;
+
+            info!(
+                "Input stream opened on '{}': {} Hz, {} channels, {:?}",
+                device_name,
+                sample_rate,
+                config.channels(),
+                config.sample_format()
+            );
+            self.stream = Some(stream);
+            self.device_name = device_name.clone();
+            self.fallback = fallback;
+            self.last_received = Instant::now();
+            self.events.emit(HellcallEvent::InputDeviceOpened {
+                device: device_name,
+                fallback,
+            });
+            return Ok(());
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| anyhow::anyhow!("No supported input config"))
+            .context(format!("Failed to open input stream on '{}'", device_name)))
+    }
+}
+
+/// 按优先级列出可尝试的输入配置：先是设备默认配置，再是设备支持的其他配置
+fn input_config_candidates(device: &cpal::Device) -> Vec<cpal::SupportedStreamConfig> {
+    let mut candidates = Vec::new();
+    match device.default_input_config() {
+        Ok(config) => candidates.push(config),
+        Err(e) => warn!("Failed to get default input config: {}", e),
+    }
+    match device.supported_input_configs() {
+        Ok(configs) => {
+            for range in configs {
+                // 优先选择不需要重采样或只需简单重采样的采样率
+                let config = range
+                    .try_with_sample_rate(cpal::SampleRate(48000))
+                    .or_else(|| range.try_with_sample_rate(cpal::SampleRate(16000)))
+                    .unwrap_or_else(|| range.with_max_sample_rate());
+                if !candidates.contains(&config) {
+                    candidates.push(config);
+                }
+            }
+        }
+        Err(e) => warn!("Failed to get supported input configs: {}", e),
+    }
+    candidates
+}
+
+/// 按配置的采样格式打开输入流，采样转换为 i16 量级的单声道 f32 后写入缓冲区
+fn build_input_stream(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    callback: StreamCallback,
+) -> Result<cpal::Stream> {
+    use cpal::SampleFormat;
+
+    let stream_config = config.config();
+    match config.sample_format() {
+        SampleFormat::I8 => callback.build::<i8>(device, &stream_config),
+        SampleFormat::I16 => callback.build::<i16>(device, &stream_config),
+        SampleFormat::I24 => callback.build::<cpal::I24>(device, &stream_config),
+        SampleFormat::I32 => callback.build::<i32>(device, &stream_config),
+        SampleFormat::I64 => callback.build::<i64>(device, &stream_config),
+        SampleFormat::U8 => callback.build::<u8>(device, &stream_config),
+        SampleFormat::U16 => callback.build::<u16>(device, &stream_config),
+        SampleFormat::U32 => callback.build::<u32>(device, &stream_config),
+        SampleFormat::U64 => callback.build::<u64>(device, &stream_config),
+        SampleFormat::F32 => callback.build::<f32>(device, &stream_config),
+        SampleFormat::F64 => callback.build::<f64>(device, &stream_config),
+        sample_format => Err(anyhow::anyhow!(
+            "Unsupported sample format {:?}",
+            sample_format
+        )),
+    }
+}
+
+/// 采样格式的正向满幅归一化后的幅度，达到它的采样视为削波
+///
+/// 无符号和低位宽格式的满幅小于 1.0，例如 U8 的 255 只有 127/128，不能统一用 [`CLIP_LEVEL`]。
+fn clip_level(format: cpal::SampleFormat) -> f32 {
+    use cpal::{FromSample, SampleFormat};
+
+    let max = match format {
+        SampleFormat::I8 => f32::from_sample_(i8::MAX),
+        SampleFormat::I16 => f32::from_sample_(i16::MAX),
+        SampleFormat::I24 => f32::from_sample_(cpal::I24::new_unchecked((1 << 23) - 1)),
+        SampleFormat::I32 => f32::from_sample_(i32::MAX),
+        SampleFormat::I64 => f32::from_sample_(i64::MAX),
+        SampleFormat::U8 => f32::from_sample_(u8::MAX),
+        SampleFormat::U16 => f32::from_sample_(u16::MAX),
+        SampleFormat::U32 => f32::from_sample_(u32::MAX),
+        SampleFormat::U64 => f32::from_sample_(u64::MAX),
+        _ => 1.0,
+    };
+    max.min(CLIP_LEVEL)
+}
+
+/// 输入流回调需要的状态
+struct StreamCallback {
+    channel_weights: Vec<f32>,
+    /// 当前采样格式的削波阈值
+    clip_level: f32,
+    producer: RingProducer,
+    stats: Arc<InputStats>,
+    received: Arc<AtomicBool>,
+    stream_error: Arc<Mutex<Option<String>>>,
+}
+
+impl StreamCallback {
+    fn build<T>(self, device: &cpal::Device, config: &cpal::StreamConfig) -> Result<cpal::Stream>
+    where
+        T: cpal::SizedSample,
+        f32: cpal::FromSample<T>,
+    {
+        use cpal::Sample;
+
+        let Self {
+            channel_weights,
+            clip_level,
+            mut producer,
+            stats,
+            received,
+            stream_error,
+        } = self;
+        let stream = device.build_input_stream(
+            config,
+            move |data: &[T], _: &_| {
+                received.store(true, Ordering::Release);
+                let frames = data.chunks(channel_weights.len());
+                let len = frames.len() as u64;
+                let mut clipped = 0;
+                let dropped = producer.push(frames.map(|frame| {
+                    frame
+                        .iter()
+                        .zip(&channel_weights)
+                        .map(|(&sample, &weight)| {
+                            let sample = f32::from_sample(sample);
+                            if sample.abs() >= clip_level {
+                                clipped += 1;
+                            }
+                            sample * 32768.0 * weight
+                        })
+                        .sum()
+                }));
+                stats.received_samples.fetch_add(len, Ordering::Relaxed);
+                if clipped > 0 {
+                    stats.clipped_samples.fetch_add(clipped, Ordering::Relaxed);
+                }
+                if dropped > 0 {
+                    stats
+                        .dropped_samples
+                        .fetch_add(dropped as u64, Ordering::Relaxed);
+                }
+            },
+            move |err| {
+                error!("an error occurred on stream: {}", err);
+                *stream_error.lock().unwrap() = Some(err.to_string());
+            },
+            None,
+        )?;
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpal::{FromSample, SampleFormat};
+
+    #[test]
+    fn full_scale_counts_as_clipped_for_every_format() {
+        // 每种格式的正向满幅都必须达到削波阈值
+        let cases = [
+            (SampleFormat::I8, f32::from_sample_(i8::MAX)),
+            (SampleFormat::I16, f32::from_sample_(i16::MAX)),
+            (SampleFormat::I32, f32::from_sample_(i32::MAX)),
+            (SampleFormat::U8, f32::from_sample_(u8::MAX)),
+            (SampleFormat::U16, f32::from_sample_(u16::MAX)),
+            (SampleFormat::F32, 1.0),
+        ];
+        for (format, max) in cases {
+            assert!(max.abs() >= clip_level(format), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn u8_clip_level_below_i16_level() {
+        assert!(clip_level(SampleFormat::U8) < CLIP_LEVEL);
+        assert_eq!(clip_level(SampleFormat::I16), CLIP_LEVEL);
+        assert_eq!(clip_level(SampleFormat::F32), CLIP_LEVEL);
+        // 负向满幅同样视为削波
+        assert!(f32::from_sample_(u8::MIN).abs() >= clip_level(SampleFormat::U8));
+    }
+}
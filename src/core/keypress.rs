@@ -1,21 +1,176 @@
 use anyhow::{Result, anyhow};
-use log::{debug, info};
+use log::{debug, info, warn};
 use rdev::{Button, EventType, Key, simulate};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{
-    collections::HashMap,
-    sync::atomic::{AtomicUsize, Ordering},
+    collections::{HashMap, VecDeque},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
     sync::mpsc,
     sync::{Arc, Mutex, RwLock},
     thread::JoinHandle,
 };
 
+use crate::core::event::{EventEmitter, HellcallEvent};
+
+/// worker 线程空闲时检查待执行战备是否过期的间隔
+const PENDING_SWEEP_INTERVAL: Duration = Duration::from_millis(100);
+/// 超过该时间没有任何按键事件时清空按住的按键
+const HELD_IDLE_RESET: Duration = Duration::from_secs(10);
+/// 系统按键自动重复的最大间隔 (含首次重复前的延迟)，按住的按键超过该时间后再次按下视为新的按下
+const REPEAT_TIMEOUT: Duration = Duration::from_millis(1500);
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Input {
     Button(Button), // 让 Unknown 优先绑定到Button
     Key(Key),
+    /// 鼠标滚轮，只能作为快捷键的触发键
+    Wheel(WheelDirection),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WheelDirection {
+    WheelUp,
+    WheelDown,
+    WheelLeft,
+    WheelRight,
+}
+
+impl Input {
+    /// Ctrl、Shift、Alt、Meta 等修饰键
+    fn is_modifier(&self) -> bool {
+        matches!(
+            self,
+            Input::Key(
+                Key::Alt
+                    | Key::AltGr
+                    | Key::ControlLeft
+                    | Key::ControlRight
+                    | Key::ShiftLeft
+                    | Key::ShiftRight
+                    | Key::MetaLeft
+                    | Key::MetaRight
+            )
+        )
+    }
+}
+
+impl WheelDirection {
+    fn from_delta(delta_x: i64, delta_y: i64) -> Option<Self> {
+        match (delta_x, delta_y) {
+            (_, y) if y > 0 => Some(Self::WheelUp),
+            (_, y) if y < 0 => Some(Self::WheelDown),
+            (x, _) if x > 0 => Some(Self::WheelRight),
+            (x, _) if x < 0 => Some(Self::WheelLeft),
+            _ => None,
+        }
+    }
+}
+
+/// 快捷键，可以是单个按键，也可以是组合键
+///
+/// 组合键的最后一个按键为触发键，其余按键需在触发时保持按下，例如:
+/// ```toml
+/// shortcut = ["Alt", "Num1"]
+/// shortcut = [{ Unknown = 1 }, "WheelUp"] # 鼠标侧键 + 滚轮
+/// ```
+///
+/// 按住快捷键之外的修饰键时不会触发，例如 `"F1"` 不响应 Ctrl+F1，`["Alt", "Num1"]` 不响应 Ctrl+Alt+Num1。
+/// `key_map` 中绑定的按键不算在内，例如 OPEN 绑定为 ControlLeft 时，按住 OPEN 仍可触发 `"F1"`。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Hotkey {
+    Single(Input),
+    Chord(Vec<Input>),
+}
+
+impl Hotkey {
+    /// 触发键
+    pub fn trigger(&self) -> Option<&Input> {
+        match self {
+            Hotkey::Single(input) => Some(input),
+            Hotkey::Chord(inputs) => inputs.last(),
+        }
+    }
+
+    /// 触发时需要保持按下的按键
+    pub fn held(&self) -> &[Input] {
+        match self {
+            Hotkey::Single(_) => &[],
+            Hotkey::Chord(inputs) => inputs.split_last().map_or(&[], |(_, held)| held),
+        }
+    }
+
+    /// 按下 `input` 时是否触发：触发键相同，组合键都已按住，且按住的修饰键恰好是组合键中的修饰键
+    ///
+    /// `key_map` 中绑定的修饰键是游戏按键，不参与修饰键的比较。
+    fn matches(
+        &self,
+        input: &Input,
+        pressed: &HeldKeys,
+        key_map: &HashMap<LocalKey, Input>,
+    ) -> bool {
+        let held = self.held();
+        self.trigger() == Some(input)
+            && held.iter().all(|k| pressed.contains(k))
+            && pressed
+                .iter()
+                .filter(|k| *k != input && k.is_modifier())
+                .filter(|k| !key_map.values().any(|v| v == *k))
+                .all(|k| held.contains(k))
+    }
+}
+
+/// 当前按住的按键，用于匹配组合键和过滤系统的按键自动重复
+///
+/// 系统钩子可能漏掉松开事件 (例如切换窗口时焦点被其它程序抢走)，残留的按键会让快捷键一直无法触发，
+/// 也会让该按键之后的按下被当作自动重复忽略，因此:
+/// - 超过 `HELD_IDLE_RESET` 没有任何按键事件时清空
+/// - 按住的按键超过 `REPEAT_TIMEOUT` 没有自动重复又被按下，视为新的按下；鼠标按键没有自动重复，再次按下总是新的按下
+#[derive(Debug, Default)]
+struct HeldKeys {
+    /// 按住的按键及其最近一次按下或自动重复的时间
+    keys: HashMap<Input, Instant>,
+    last_event: Option<Instant>,
+}
+
+impl HeldKeys {
+    /// 记录按下，返回是否为新的按下，否则是系统的自动重复
+    fn press(&mut self, input: Input, now: Instant) -> bool {
+        self.touch(now);
+        let is_button = matches!(input, Input::Button(_));
+        match self.keys.insert(input, now) {
+            None => true,
+            Some(_) if is_button => true,
+            Some(last) => now.saturating_duration_since(last) >= REPEAT_TIMEOUT,
+        }
+    }
+
+    fn release(&mut self, input: &Input, now: Instant) {
+        self.touch(now);
+        self.keys.remove(input);
+    }
+
+    fn contains(&self, input: &Input) -> bool {
+        self.keys.contains_key(input)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Input> {
+        self.keys.keys()
+    }
+
+    fn touch(&mut self, now: Instant) {
+        if self
+            .last_event
+            .is_some_and(|last| now.saturating_duration_since(last) >= HELD_IDLE_RESET)
+            && !self.keys.is_empty()
+        {
+            debug!("no key event for a while, reset held keys: {:?}", self.keys);
+            self.keys.clear();
+        }
+        self.last_event = Some(now);
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -28,10 +183,12 @@ pub enum LocalKey {
     OPEN,
     /// 扔出战备, 一般是鼠标左键
     THROW,
-    /// 重新执行上一次键盘宏按键
+    /// 重新装填上一次执行的战备
     RESEND,
-    /// Push-to-Talk 按住说话
+    /// Push-to-Talk 说话键，具体行为由 `talk_mode` 决定
     PTT,
+    /// 启用/暂停 hellcall，暂停期间不识别语音也不响应快捷键，例如在游戏内打字聊天时
+    TOGGLE,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +199,35 @@ pub struct KeyPresserConfig {
     pub key_release_interval: u64,
     /// 按键间隔
     pub diff_key_interval: u64,
+    /// 待执行战备队列的最大长度，队列已满时挤出最早入队的战备
+    #[serde(default = "default_pending_capacity")]
+    pub pending_capacity: usize,
+    /// 待执行战备的出队顺序
+    #[serde(default)]
+    pub pending_order: PendingOrder,
+    /// 待执行战备的存活时间 (毫秒)，超时未按 OPEN 则丢弃，不设置表示永不过期
+    #[serde(default)]
+    pub pending_expire_time: Option<u64>,
+    /// 待执行战备过期时随机播放的提示音效
+    #[serde(default)]
+    pub expired_audio_files: Vec<String>,
+    /// 已执行战备历史记录的最大长度，供 RESEND 和按序号重发使用
+    #[serde(default = "default_history_capacity")]
+    pub history_capacity: usize,
+    /// 通过 TOGGLE 键启用时随机播放的提示音效
+    #[serde(default)]
+    pub enabled_audio_files: Vec<String>,
+    /// 通过 TOGGLE 键暂停时随机播放的提示音效
+    #[serde(default)]
+    pub disabled_audio_files: Vec<String>,
+}
+
+fn default_pending_capacity() -> usize {
+    1
+}
+
+fn default_history_capacity() -> usize {
+    10
 }
 
 impl Default for KeyPresserConfig {
@@ -50,22 +236,87 @@ impl Default for KeyPresserConfig {
             wait_open_time: 30,
             key_release_interval: 30,
             diff_key_interval: 20,
+            pending_capacity: default_pending_capacity(),
+            pending_order: PendingOrder::default(),
+            pending_expire_time: None,
+            expired_audio_files: Vec::new(),
+            history_capacity: default_history_capacity(),
+            enabled_audio_files: Vec::new(),
+            disabled_audio_files: Vec::new(),
         }
     }
 }
 
+/// 待执行战备队列的出队顺序，每按一次 OPEN 取出一个
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PendingOrder {
+    /// 先进先出，按呼叫顺序依次执行
+    #[serde(rename = "fifo")]
+    Fifo,
+    /// 后进先出，优先执行最近一次呼叫
+    #[serde(rename = "lifo")]
+    #[default]
+    Lifo,
+}
+
+/// 快捷键或语音指令触发的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroAction {
+    /// 执行按键序列
+    Keys(Vec<LocalKey>),
+    /// 将历史记录中第 N 个最近执行的战备重新放入待执行队列，1 表示最近一次
+    ///
+    /// 重新装填的战备执行后不会再次记入历史，其它战备的序号保持不变。
+    Recall(usize),
+}
+
+/// 待执行的战备
+struct PendingStratagem {
+    keys: Vec<LocalKey>,
+    queued_at: Instant,
+    /// 是否从历史记录中重新装填
+    recalled: bool,
+}
+
+/// 交给 worker 线程执行的按键序列
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MacroJob {
+    keys: Vec<LocalKey>,
+    /// 从历史记录中重新装填的战备，执行后不再记入历史
+    recalled: bool,
+}
+
+impl MacroJob {
+    fn new(keys: Vec<LocalKey>) -> Self {
+        Self {
+            keys,
+            recalled: false,
+        }
+    }
+}
+
+type ExpiredCallback = Box<dyn Fn(&[LocalKey]) + Send + 'static>;
+type ToggleCallback = Box<dyn Fn(bool) + Send + 'static>;
+
 pub struct KeyPresser {
     config: Arc<RwLock<KeyPresserConfig>>,
     /// 按键映射
     key_map: Arc<RwLock<HashMap<LocalKey, Input>>>,
-    shortcut: Arc<RwLock<HashMap<Input, Vec<LocalKey>>>>,
-    one_stack: Arc<Mutex<Option<Vec<LocalKey>>>>,
-    spare_stack: Arc<Mutex<Option<Vec<LocalKey>>>>,
-    tx: Option<mpsc::Sender<Vec<LocalKey>>>,
+    shortcut: Arc<RwLock<HashMap<Hotkey, MacroAction>>>,
+    /// 待执行的战备队列，等待玩家按下 OPEN 时消费
+    pending: Arc<Mutex<VecDeque<PendingStratagem>>>,
+    /// 已执行的战备历史，最近一次在最前
+    history: Arc<Mutex<VecDeque<Vec<LocalKey>>>>,
+    tx: Option<mpsc::Sender<MacroJob>>,
     worker_handle: Option<JoinHandle<()>>,
     /// 当前正在模拟按键的数量，用于 listen 回调过滤注入事件
     simulating: Arc<AtomicUsize>,
     listen_key_map: Arc<Mutex<HashMap<Input, Box<dyn FnMut(bool) + Send + 'static>>>>,
+    events: EventEmitter,
+    on_expired: Arc<Mutex<Option<ExpiredCallback>>>,
+    /// 是否启用，由 TOGGLE 键切换
+    enabled: Arc<AtomicBool>,
+    on_toggle: Arc<Mutex<Option<ToggleCallback>>>,
 }
 
 impl KeyPresser {
@@ -83,6 +334,29 @@ impl KeyPresser {
                 return Err(anyhow!("Missing mapping for LocalKey: {:?}", local_key));
             }
         }
+        for (local_key, input) in key_map {
+            if let Input::Wheel(_) = input {
+                return Err(anyhow!(
+                    "Cannot map LocalKey {:?} to mouse wheel",
+                    local_key
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_shortcut(shortcut: &HashMap<Hotkey, MacroAction>) -> Result<()> {
+        for hotkey in shortcut.keys() {
+            if hotkey.trigger().is_none() {
+                return Err(anyhow!("shortcut must not be empty"));
+            }
+            if hotkey.held().iter().any(|k| matches!(k, Input::Wheel(_))) {
+                return Err(anyhow!(
+                    "mouse wheel can only be the last key of shortcut: {:?}",
+                    hotkey
+                ));
+            }
+        }
         Ok(())
     }
 
@@ -90,9 +364,10 @@ impl KeyPresser {
         &self,
         config: KeyPresserConfig,
         key_map: HashMap<LocalKey, Input>,
-        shortcut: HashMap<Input, Vec<LocalKey>>,
+        shortcut: HashMap<Hotkey, MacroAction>,
     ) -> Result<()> {
         Self::check_key_map(&key_map)?;
+        Self::check_shortcut(&shortcut)?;
         *self.config.write().unwrap() = config;
         *self.key_map.write().unwrap() = key_map;
         *self.shortcut.write().unwrap() = shortcut;
@@ -102,19 +377,28 @@ impl KeyPresser {
     pub fn new(
         config: KeyPresserConfig,
         key_map: HashMap<LocalKey, Input>,
-        shortcut: HashMap<Input, Vec<LocalKey>>,
+        shortcut: HashMap<Hotkey, MacroAction>,
     ) -> Result<Self> {
         Self::check_key_map(&key_map)?;
+        Self::check_shortcut(&shortcut)?;
 
         // keypress worker
-        let (tx, rx) = std::sync::mpsc::channel::<Vec<LocalKey>>();
+        let (tx, rx) = std::sync::mpsc::channel::<MacroJob>();
         let config = Arc::new(RwLock::new(config));
         let key_map = Arc::new(RwLock::new(key_map));
         let simulating = Arc::new(AtomicUsize::new(0));
+        let pending = Arc::new(Mutex::new(VecDeque::new()));
+        let history = Arc::new(Mutex::new(VecDeque::new()));
+        let events = EventEmitter::new();
+        let on_expired: Arc<Mutex<Option<ExpiredCallback>>> = Arc::new(Mutex::new(None));
         let handle = std::thread::spawn({
             let config = Arc::clone(&config);
             let key_map = Arc::clone(&key_map);
             let simulating = Arc::clone(&simulating);
+            let pending = Arc::clone(&pending);
+            let history = Arc::clone(&history);
+            let events = events.clone();
+            let on_expired = Arc::clone(&on_expired);
             move || {
                 // 定义一个模拟按键的闭包，自动维护 simulating 计数，确保 listen 回调能正确过滤注入事件
                 let sim = |event: &EventType| {
@@ -123,8 +407,20 @@ impl KeyPresser {
                     }
                 };
 
-                while let Ok(keys) = rx.recv() {
-                    info!("key pressed: {:?}", keys);
+                loop {
+                    let job = match rx.recv_timeout(PENDING_SWEEP_INTERVAL) {
+                        Ok(job) => job,
+                        Err(mpsc::RecvTimeoutError::Timeout) => {
+                            // 空闲时清理过期的待执行战备，使提示音在过期时刻及时播放
+                            let expire_time = config.read().unwrap().pending_expire_time;
+                            let expired =
+                                Self::take_expired(&mut pending.lock().unwrap(), expire_time);
+                            Self::notify_expired(expired, &events, &on_expired);
+                            continue;
+                        }
+                        Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                    };
+                    info!("key pressed: {:?}", job.keys);
 
                     simulating.fetch_add(1, Ordering::Relaxed);
 
@@ -136,13 +432,18 @@ impl KeyPresser {
                             c.diff_key_interval.clone(),
                         )
                     };
+                    let history_capacity = c.history_capacity;
                     drop(c);
 
+                    // 所有实际执行的宏都在这里记入历史，包括快捷键和以 OPEN 开头的语音指令
+                    Self::remember(&history, &job, history_capacity);
+
                     // convert keys to events
                     let km = key_map.read().unwrap();
-                    let key_event_map: Vec<(LocalKey, EventType, EventType)> = keys
+                    let key_event_map: Vec<(LocalKey, EventType, EventType)> = job
+                        .keys
                         .iter()
-                        .map(|k| {
+                        .filter_map(|k| {
                             let local_key = k.clone();
                             let input = km.get(k).unwrap().clone();
                             let (press_event_type, release_event_type) = match input {
@@ -153,8 +454,10 @@ impl KeyPresser {
                                     EventType::ButtonPress(button),
                                     EventType::ButtonRelease(button),
                                 ),
+                                // check_key_map 已拒绝滚轮映射
+                                Input::Wheel(_) => return None,
                             };
-                            (local_key, press_event_type, release_event_type)
+                            Some((local_key, press_event_type, release_event_type))
                         })
                         .collect();
                     drop(km);
@@ -197,28 +500,190 @@ impl KeyPresser {
             config,
             key_map,
             shortcut: Arc::new(RwLock::new(shortcut)),
-            one_stack: Arc::new(Mutex::new(None)),
-            spare_stack: Arc::new(Mutex::new(None)),
+            pending,
+            history,
             tx: Some(tx),
             worker_handle: Some(handle),
             simulating,
             listen_key_map: Arc::new(Mutex::new(HashMap::new())),
+            events,
+            on_expired,
+            enabled: Arc::new(AtomicBool::new(true)),
+            on_toggle: Arc::new(Mutex::new(None)),
         })
     }
 
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// 注册启用状态切换时的回调，参数为切换后的状态，会替换掉已有的回调
+    pub fn on_toggle<F>(&self, callback: F)
+    where
+        F: Fn(bool) + Send + 'static,
+    {
+        *self.on_toggle.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// 事件分发器，KeyPresser 在引擎重启时会被复用，因此监听器也随之保留
+    pub fn events(&self) -> EventEmitter {
+        self.events.clone()
+    }
+
+    /// 注册待执行战备过期时的回调，会替换掉已有的回调
+    pub fn on_pending_expired<F>(&self, callback: F)
+    where
+        F: Fn(&[LocalKey]) + Send + 'static,
+    {
+        *self.on_expired.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// 将战备放入待执行队列，队列已满时挤出最早入队的战备
+    fn enqueue(
+        pending: &mut VecDeque<PendingStratagem>,
+        keys: Vec<LocalKey>,
+        recalled: bool,
+        capacity: usize,
+        events: &EventEmitter,
+    ) {
+        while pending.len() >= capacity.max(1) {
+            if let Some(dropped) = pending.pop_front() {
+                info!("pending stratagem overwritten: {:?}", &dropped.keys);
+                events.emit(HellcallEvent::StratagemOverwritten { keys: dropped.keys });
+            }
+        }
+        pending.push_back(PendingStratagem {
+            keys,
+            queued_at: Instant::now(),
+            recalled,
+        });
+    }
+
+    fn dequeue(pending: &mut VecDeque<PendingStratagem>, order: PendingOrder) -> Option<MacroJob> {
+        let item = match order {
+            PendingOrder::Fifo => pending.pop_front(),
+            PendingOrder::Lifo => pending.pop_back(),
+        };
+        item.map(|p| MacroJob {
+            keys: p.keys,
+            recalled: p.recalled,
+        })
+    }
+
+    /// 从队列中移除超过存活时间的战备并返回
+    fn take_expired(
+        pending: &mut VecDeque<PendingStratagem>,
+        expire_time: Option<u64>,
+    ) -> Vec<Vec<LocalKey>> {
+        let Some(expire_time) = expire_time else {
+            return Vec::new();
+        };
+        let expire_time = Duration::from_millis(expire_time);
+        let mut expired = Vec::new();
+        pending.retain(|p| {
+            if p.queued_at.elapsed() >= expire_time {
+                expired.push(p.keys.clone());
+                false
+            } else {
+                true
+            }
+        });
+        expired
+    }
+
+    /// 发送过期事件并调用过期回调，回调锁被占用时跳过，可在系统钩子中调用
+    fn notify_expired(
+        expired: Vec<Vec<LocalKey>>,
+        events: &EventEmitter,
+        on_expired: &Mutex<Option<ExpiredCallback>>,
+    ) {
+        for keys in expired {
+            info!("pending stratagem expired: {:?}", &keys);
+            if let Ok(callback) = on_expired.try_lock()
+                && let Some(callback) = callback.as_ref()
+            {
+                callback(&keys);
+            }
+            events.emit(HellcallEvent::StratagemExpired { keys });
+        }
+    }
+
+    /// 记录一次已执行的战备
+    ///
+    /// 历史中的战备会被重新放入待执行队列，由玩家按 OPEN 执行，因此去掉开头的 OPEN。
+    /// 重新装填的战备不再记录，否则每次按序号重发都会改变其它战备的序号。
+    fn remember(history: &Mutex<VecDeque<Vec<LocalKey>>>, job: &MacroJob, capacity: usize) {
+        if job.recalled {
+            return;
+        }
+        let keys = job.keys.as_slice();
+        let keys = keys.strip_prefix(&[LocalKey::OPEN]).unwrap_or(keys);
+        if keys.is_empty() {
+            return;
+        }
+        let mut history = history.lock().unwrap();
+        history.push_front(keys.to_vec());
+        history.truncate(capacity.max(1));
+    }
+
+    /// 将历史记录中第 `index` 个最近执行的战备重新放入待执行队列
+    ///
+    /// 先读 history，再写 pending，避免同时持有两把锁（防死锁）。
+    /// 全程使用 try_lock，可在系统钩子中调用。
+    fn recall_from(
+        history: &Mutex<VecDeque<Vec<LocalKey>>>,
+        pending: &Mutex<VecDeque<PendingStratagem>>,
+        index: usize,
+        capacity: usize,
+        events: &EventEmitter,
+    ) {
+        let keys = history
+            .try_lock()
+            .ok()
+            .and_then(|h| h.get(index.checked_sub(1)?).cloned());
+        let Some(keys) = keys else {
+            warn!("no stratagem #{} in history", index);
+            return;
+        };
+        info!("recall stratagem #{}: {:?}", index, &keys);
+        if let Ok(mut guard) = pending.try_lock() {
+            Self::enqueue(&mut guard, keys, true, capacity, events);
+        }
+    }
+
+    /// 重新装填第 `index` 个最近执行的战备，1 表示最近一次
+    pub fn recall(&self, index: usize) {
+        let capacity = self.config.read().unwrap().pending_capacity;
+        Self::recall_from(&self.history, &self.pending, index, capacity, &self.events);
+    }
+
+    /// 执行快捷键或语音指令对应的动作
+    pub fn execute(&self, action: &MacroAction) {
+        match action {
+            MacroAction::Keys(keys) => self.push(keys),
+            MacroAction::Recall(index) => self.recall(*index),
+        }
+    }
+
     pub fn push(&self, keys: &[LocalKey]) {
         let keys = keys.to_vec();
 
         if let Some(first_key) = keys.first() {
             if first_key == &LocalKey::OPEN {
                 if let Some(tx) = &self.tx {
-                    if let Err(e) = tx.send(keys.clone()) {
+                    if let Err(e) = tx.send(MacroJob::new(keys.clone())) {
                         log::error!("push send error: {:?}", e);
                     }
                 }
             } else {
-                *self.one_stack.lock().unwrap() = Some(keys.clone());
-                *self.spare_stack.lock().unwrap() = Some(keys.clone());
+                let capacity = self.config.read().unwrap().pending_capacity;
+                Self::enqueue(
+                    &mut self.pending.lock().unwrap(),
+                    keys,
+                    false,
+                    capacity,
+                    &self.events,
+                );
             }
         }
     }
@@ -226,7 +691,7 @@ impl KeyPresser {
     /// 注册一个全局按键监听器
     ///
     /// 当指定的按键 `key` 被按下或释放时，会触发 `callback` 函数。
-    /// `callback` 的参数为 `true` 表示按下，`false` 表示释放。
+    /// `callback` 的参数为 `true` 表示按下，`false` 表示释放，按住时系统的自动重复不会触发回调。
     pub fn listen_key<F>(&self, key: Input, callback: F) -> Result<()>
     where
         F: FnMut(bool) + Send + 'static,
@@ -240,17 +705,43 @@ impl KeyPresser {
 
     /// block
     pub fn listen(&self) -> Result<()> {
+        let config = Arc::clone(&self.config);
         let shortcut = Arc::clone(&self.shortcut);
         let key_map = Arc::clone(&self.key_map);
-        let one_stack = Arc::clone(&self.one_stack);
-        let spare_stack = Arc::clone(&self.spare_stack);
+        let pending = Arc::clone(&self.pending);
+        let history = Arc::clone(&self.history);
         let tx = self.tx.as_ref().unwrap().clone();
         let simulating = Arc::clone(&self.simulating);
         let listen_key_map = Arc::clone(&self.listen_key_map);
+        let events = self.events.clone();
+        let on_expired = Arc::clone(&self.on_expired);
+        let enabled = Arc::clone(&self.enabled);
+        let on_toggle = Arc::clone(&self.on_toggle);
+        let mut held = HeldKeys::default();
 
         // block
         rdev::listen(move |event| {
-            // 首先处理注册的监听按键（包括按下和松开），不受 simulate 状态影响
+            // 先更新按住的按键，已经按住的按键再次收到按下事件，说明是系统的自动重复。
+            // 松开事件总是处理，避免 simulate 期间漏掉松开导致按键状态残留
+            let now = Instant::now();
+            let is_repeat = match event.event_type {
+                EventType::KeyPress(key) => !held.press(Input::Key(key), now),
+                EventType::ButtonPress(button) => !held.press(Input::Button(button), now),
+                EventType::KeyRelease(key) => {
+                    held.release(&Input::Key(key), now);
+                    false
+                }
+                EventType::ButtonRelease(button) => {
+                    held.release(&Input::Button(button), now);
+                    false
+                }
+                _ => false,
+            };
+            if is_repeat {
+                return;
+            }
+
+            // 处理注册的监听按键（包括按下和松开），不受 simulate 状态影响
             if let Ok(mut listeners) = listen_key_map.try_lock() {
                 for (input, callback) in listeners.iter_mut() {
                     let is_press = match (&event.event_type, input) {
@@ -280,47 +771,92 @@ impl KeyPresser {
             let Some(input) = (match event.event_type {
                 EventType::KeyPress(key) => Some(Input::Key(key)),
                 EventType::ButtonPress(key) => Some(Input::Button(key)),
+                EventType::Wheel { delta_x, delta_y } => {
+                    WheelDirection::from_delta(delta_x, delta_y).map(Input::Wheel)
+                }
                 _ => None,
             }) else {
                 return;
             };
 
-            let (open_key, resend_key) = {
+            let (open_key, resend_key, toggle_key) = {
                 let Ok(km) = key_map.try_read() else {
                     return;
                 };
                 (
                     km.get(&LocalKey::OPEN).unwrap().clone(),
                     km.get(&LocalKey::RESEND).unwrap().clone(),
+                    km.get(&LocalKey::TOGGLE).cloned(),
                 )
             };
 
+            if toggle_key.as_ref() == Some(&input) {
+                let is_enabled = !enabled.fetch_xor(true, Ordering::AcqRel);
+                info!(
+                    "hellcall {}",
+                    if is_enabled { "enabled" } else { "disabled" }
+                );
+                if let Ok(callback) = on_toggle.try_lock()
+                    && let Some(callback) = callback.as_ref()
+                {
+                    callback(is_enabled);
+                }
+                events.emit(HellcallEvent::EnabledChanged {
+                    enabled: is_enabled,
+                });
+                return;
+            }
+
+            // 暂停期间不响应任何按键
+            if !enabled.load(Ordering::Acquire) {
+                return;
+            }
+            let (capacity, order, expire_time) = {
+                let Ok(c) = config.try_read() else {
+                    return;
+                };
+                (c.pending_capacity, c.pending_order, c.pending_expire_time)
+            };
+
             if input == open_key {
                 // 使用 try_lock 非阻塞：若锁被占用则跳过，绝不阻塞系统钩子
-                if let Ok(mut guard) = one_stack.try_lock() {
-                    if let Some(keys) = guard.take() {
-                        if let Err(e) = tx.send(keys) {
-                            log::error!("listen send error: {:?}", e);
-                        }
-                    }
+                let mut expired = Vec::new();
+                let mut job_opt = None;
+                if let Ok(mut guard) = pending.try_lock() {
+                    // 先丢弃已过期的战备，避免执行玩家早已忘记的指令
+                    expired = Self::take_expired(&mut guard, expire_time);
+                    job_opt = Self::dequeue(&mut guard, order);
                 }
-            } else if input == resend_key {
-                // 先读 spare_stack，再写 one_stack，避免同时持有两把锁（防死锁）
-                let keys_opt = spare_stack.try_lock().ok().and_then(|g| g.clone());
-                if let Some(keys) = keys_opt {
-                    info!("resend key press: {:?}", &keys);
-                    if let Ok(mut guard) = one_stack.try_lock() {
-                        guard.replace(keys);
-                    }
+                if let Some(job) = job_opt
+                    && let Err(e) = tx.send(job)
+                {
+                    log::error!("listen send error: {:?}", e);
                 }
+                Self::notify_expired(expired, &events, &on_expired);
+            } else if input == resend_key {
+                Self::recall_from(&history, &pending, 1, capacity, &events);
             } else {
-                let Ok(sc) = shortcut.try_read() else {
+                let (Ok(sc), Ok(km)) = (shortcut.try_read(), key_map.try_read()) else {
                     return;
                 };
-                if let Some(keys) = sc.get(&input).cloned() {
-                    drop(sc);
-                    if let Err(e) = tx.send(keys) {
-                        log::error!("shortcut send error: {:?}", e);
+                // 多个快捷键同时满足时优先组合键最多的
+                let action = sc
+                    .iter()
+                    .filter(|(hotkey, _)| hotkey.matches(&input, &held, &km))
+                    .max_by_key(|(hotkey, _)| hotkey.held().len())
+                    .map(|(_, action)| action.clone());
+                drop(sc);
+                drop(km);
+                if let Some(action) = action {
+                    match action {
+                        MacroAction::Keys(keys) => {
+                            if let Err(e) = tx.send(MacroJob::new(keys)) {
+                                log::error!("shortcut send error: {:?}", e);
+                            }
+                        }
+                        MacroAction::Recall(index) => {
+                            Self::recall_from(&history, &pending, index, capacity, &events);
+                        }
                     }
                 }
             }
@@ -330,6 +866,14 @@ impl KeyPresser {
         Ok(())
     }
 
+    pub fn check_action(action: &MacroAction) -> Result<()> {
+        match action {
+            MacroAction::Keys(keys) => Self::has_validity(keys),
+            MacroAction::Recall(0) => Err(anyhow!("recall index must start from 1")),
+            MacroAction::Recall(_) => Ok(()),
+        }
+    }
+
     pub fn has_validity(keys: &[LocalKey]) -> Result<()> {
         if keys.is_empty() {
             return Err(anyhow!("keys must not be empty"));
@@ -339,6 +883,10 @@ impl KeyPresser {
             return Err(anyhow!("cannot use RESEND key in a macro"));
         }
 
+        if keys.contains(&LocalKey::TOGGLE) {
+            return Err(anyhow!("cannot use TOGGLE key in a macro"));
+        }
+
         let has_open = keys.contains(&LocalKey::OPEN);
         let has_throw = keys.contains(&LocalKey::THROW);
 
@@ -379,3 +927,232 @@ impl Drop for KeyPresser {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(
+        calls: &[&[LocalKey]],
+        capacity: usize,
+        events: &EventEmitter,
+    ) -> VecDeque<PendingStratagem> {
+        let mut pending = VecDeque::new();
+        for keys in calls {
+            KeyPresser::enqueue(&mut pending, keys.to_vec(), false, capacity, events);
+        }
+        pending
+    }
+
+    fn next(
+        pending: &mut VecDeque<PendingStratagem>,
+        order: PendingOrder,
+    ) -> Option<Vec<LocalKey>> {
+        KeyPresser::dequeue(pending, order).map(|job| job.keys)
+    }
+
+    /// 收集发送的事件
+    fn collect_events() -> (EventEmitter, Arc<Mutex<Vec<HellcallEvent>>>) {
+        let events = EventEmitter::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&received);
+        events.set_listener(move |event| sink.lock().unwrap().push(event));
+        (events, received)
+    }
+
+    #[test]
+    fn fifo_dequeues_in_call_order() {
+        let events = EventEmitter::new();
+        let mut pending = queue(&[&[LocalKey::UP], &[LocalKey::DOWN]], 3, &events);
+        let order = PendingOrder::Fifo;
+        assert_eq!(next(&mut pending, order), Some(vec![LocalKey::UP]));
+        assert_eq!(next(&mut pending, order), Some(vec![LocalKey::DOWN]));
+        assert_eq!(next(&mut pending, order), None);
+    }
+
+    #[test]
+    fn lifo_dequeues_latest_first() {
+        let events = EventEmitter::new();
+        let mut pending = queue(&[&[LocalKey::UP], &[LocalKey::DOWN]], 3, &events);
+        let order = PendingOrder::Lifo;
+        assert_eq!(next(&mut pending, order), Some(vec![LocalKey::DOWN]));
+        assert_eq!(next(&mut pending, order), Some(vec![LocalKey::UP]));
+        assert_eq!(next(&mut pending, order), None);
+    }
+
+    #[test]
+    fn full_queue_overwrites_oldest() {
+        let (events, received) = collect_events();
+        let mut pending = queue(
+            &[&[LocalKey::UP], &[LocalKey::DOWN], &[LocalKey::LEFT]],
+            2,
+            &events,
+        );
+        assert_eq!(pending.len(), 2);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert!(matches!(
+            &received[0],
+            HellcallEvent::StratagemOverwritten { keys } if keys == &[LocalKey::UP]
+        ));
+
+        let order = PendingOrder::Fifo;
+        assert_eq!(next(&mut pending, order), Some(vec![LocalKey::DOWN]));
+        assert_eq!(next(&mut pending, order), Some(vec![LocalKey::LEFT]));
+    }
+
+    #[test]
+    fn zero_ttl_expires_everything() {
+        let events = EventEmitter::new();
+        let mut pending = queue(&[&[LocalKey::UP], &[LocalKey::DOWN]], 3, &events);
+        let expired = KeyPresser::take_expired(&mut pending, Some(0));
+        assert_eq!(expired, vec![vec![LocalKey::UP], vec![LocalKey::DOWN]]);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn pending_without_ttl_never_expires() {
+        let events = EventEmitter::new();
+        let mut pending = queue(&[&[LocalKey::UP]], 3, &events);
+        assert!(KeyPresser::take_expired(&mut pending, None).is_empty());
+        assert!(KeyPresser::take_expired(&mut pending, Some(60_000)).is_empty());
+        assert_eq!(pending.len(), 1);
+    }
+
+    #[test]
+    fn zero_capacity_keeps_latest() {
+        let (events, received) = collect_events();
+        let mut pending = queue(&[&[LocalKey::UP], &[LocalKey::DOWN]], 0, &events);
+        assert_eq!(received.lock().unwrap().len(), 1);
+        assert_eq!(
+            next(&mut pending, PendingOrder::Lifo),
+            Some(vec![LocalKey::DOWN])
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn recall_keeps_history_indices() {
+        let events = EventEmitter::new();
+        let history = Mutex::new(VecDeque::new());
+        let pending = Mutex::new(VecDeque::new());
+        for keys in [
+            vec![LocalKey::OPEN, LocalKey::UP],
+            vec![LocalKey::OPEN, LocalKey::DOWN],
+        ] {
+            KeyPresser::remember(&history, &MacroJob::new(keys), 10);
+        }
+
+        // 重发第 2 个并执行，历史记录不变，再次重发第 2 个仍是同一个战备
+        for _ in 0..2 {
+            KeyPresser::recall_from(&history, &pending, 2, 1, &events);
+            let job =
+                KeyPresser::dequeue(&mut pending.lock().unwrap(), PendingOrder::Lifo).unwrap();
+            assert_eq!(job.keys, [LocalKey::UP]);
+            assert!(job.recalled);
+            KeyPresser::remember(&history, &job, 10);
+        }
+        assert_eq!(
+            *history.lock().unwrap(),
+            [vec![LocalKey::DOWN], vec![LocalKey::UP]]
+        );
+    }
+
+    fn held(keys: &[Key], now: Instant) -> HeldKeys {
+        let mut held = HeldKeys::default();
+        for &key in keys {
+            held.press(Input::Key(key), now);
+        }
+        held
+    }
+
+    fn key_map() -> HashMap<LocalKey, Input> {
+        HashMap::from([(LocalKey::OPEN, Input::Key(Key::ControlLeft))])
+    }
+
+    #[test]
+    fn single_hotkey_rejects_extra_modifier() {
+        let now = Instant::now();
+        let f1 = Input::Key(Key::F1);
+        let hotkey = Hotkey::Single(f1.clone());
+        let key_map = key_map();
+
+        assert!(hotkey.matches(&f1, &held(&[Key::F1], now), &key_map));
+        assert!(!hotkey.matches(&Input::Key(Key::F2), &held(&[Key::F2], now), &key_map));
+        let with_alt = held(&[Key::Alt, Key::F1], now);
+        assert!(!hotkey.matches(&f1, &with_alt, &key_map));
+        // 非修饰键不影响
+        let with_w = held(&[Key::KeyW, Key::F1], now);
+        assert!(hotkey.matches(&f1, &with_w, &key_map));
+    }
+
+    #[test]
+    fn bound_modifier_does_not_block_hotkey() {
+        let now = Instant::now();
+        let f1 = Input::Key(Key::F1);
+        let hotkey = Hotkey::Single(f1.clone());
+        let with_open = held(&[Key::ControlLeft, Key::F1], now);
+        assert!(hotkey.matches(&f1, &with_open, &key_map()));
+        assert!(!hotkey.matches(&f1, &with_open, &HashMap::new()));
+    }
+
+    #[test]
+    fn chord_requires_held_keys() {
+        let now = Instant::now();
+        let alt = Input::Key(Key::Alt);
+        let num1 = Input::Key(Key::Num1);
+        let hotkey = Hotkey::Chord(vec![alt.clone(), num1.clone()]);
+        let key_map = key_map();
+
+        assert!(hotkey.matches(&num1, &held(&[Key::Alt, Key::Num1], now), &key_map));
+        assert!(!hotkey.matches(&num1, &held(&[Key::Num1], now), &key_map));
+        let with_shift = held(&[Key::ShiftLeft, Key::Alt, Key::Num1], now);
+        assert!(!hotkey.matches(&num1, &with_shift, &key_map));
+    }
+
+    #[test]
+    fn stale_modifier_is_cleared_after_idle() {
+        let start = Instant::now();
+        let f1 = Input::Key(Key::F1);
+        let hotkey = Hotkey::Single(f1.clone());
+
+        // 漏掉了 Alt 的松开事件
+        let mut held = held(&[Key::Alt], start);
+        held.press(f1.clone(), start + Duration::from_secs(1));
+        assert!(!hotkey.matches(&f1, &held, &key_map()));
+        held.release(&f1, start + Duration::from_secs(2));
+
+        held.press(f1.clone(), start + Duration::from_secs(2) + HELD_IDLE_RESET);
+        assert!(hotkey.matches(&f1, &held, &key_map()));
+    }
+
+    #[test]
+    fn press_after_lost_release_is_not_repeat() {
+        let start = Instant::now();
+        let key = Input::Key(Key::KeyV);
+        let mut held = HeldKeys::default();
+
+        assert!(held.press(key.clone(), start));
+        // 系统的自动重复
+        let mut now = start + Duration::from_millis(500);
+        assert!(!held.press(key.clone(), now));
+        now += Duration::from_millis(30);
+        assert!(!held.press(key.clone(), now));
+
+        // 漏掉了松开事件，之后的按下仍然生效
+        now += REPEAT_TIMEOUT;
+        assert!(held.press(key.clone(), now));
+        held.release(&key, now + Duration::from_millis(100));
+        assert!(!held.contains(&key));
+    }
+
+    #[test]
+    fn button_press_is_never_repeat() {
+        let now = Instant::now();
+        let button = Input::Button(Button::Unknown(1));
+        let mut held = HeldKeys::default();
+        assert!(held.press(button.clone(), now));
+        assert!(held.press(button, now + Duration::from_millis(10)));
+    }
+}
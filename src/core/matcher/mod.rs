@@ -1,3 +1,5 @@
 pub mod levenshtein;
+pub mod speech;
 
 pub use levenshtein::*;
+pub use speech::*;
@@ -0,0 +1,85 @@
+use log::{debug, info, warn};
+use serde::Serialize;
+
+use super::LevenshteinMatcher;
+use crate::core::audio::RecognitionResult;
+
+/// 一次识别结果的匹配结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum MatchOutcome {
+    /// 识别结果为空
+    Empty,
+    /// 语音过短或字数过少，被当作噪音过滤
+    Filtered,
+    /// 设置了唤醒词但没有说出唤醒词
+    MissHitWord { speech: String },
+    /// 没有匹配到指令
+    NoMatch { input: String },
+    /// 匹配到指令
+    Hit { input: String, command: String },
+}
+
+impl MatchOutcome {
+    /// 匹配到的指令
+    pub fn command(&self) -> Option<&str> {
+        match self {
+            MatchOutcome::Hit { command, .. } => Some(command),
+            _ => None,
+        }
+    }
+}
+
+/// 最终识别结果的匹配结果，被过滤的结果不参与匹配
+pub fn match_result(
+    result: &RecognitionResult,
+    hit_word: Option<&str>,
+    matcher: &mut LevenshteinMatcher,
+) -> MatchOutcome {
+    if result.filtered {
+        debug!("filtered short utterance: {}", result.text);
+        return MatchOutcome::Filtered;
+    }
+    match_speech(&result.text, hit_word, matcher)
+}
+
+/// 去掉识别结果中的空格和唤醒词，再与指令匹配
+pub fn match_speech(
+    text: &str,
+    hit_word: Option<&str>,
+    matcher: &mut LevenshteinMatcher,
+) -> MatchOutcome {
+    let speech = text.trim();
+    if speech.is_empty() {
+        return MatchOutcome::Empty;
+    }
+
+    let speech = speech.replace(" ", "");
+    let input = match hit_word.filter(|x| !x.is_empty()) {
+        None => {
+            info!("speech: {}", speech);
+            speech
+        }
+        Some(hit_word) => {
+            if let Some(pos) = speech.rfind(hit_word) {
+                let command_str = &speech[pos + hit_word.len()..];
+                info!("speech: {} {}", hit_word, command_str);
+                command_str.to_string()
+            } else {
+                warn!("miss required word '{}': {}", hit_word, speech);
+                return MatchOutcome::MissHitWord { speech };
+            }
+        }
+    };
+
+    match matcher.match_str(input.as_str()) {
+        Some(command) => {
+            info!("hit command: {}", command);
+            MatchOutcome::Hit { input, command }
+        }
+        None => {
+            warn!("no matching command found: {}", input);
+            MatchOutcome::NoMatch { input }
+        }
+    }
+}
@@ -1,5 +1,13 @@
 pub mod audio;
 pub mod command;
+pub mod device;
+pub mod event;
+pub mod gain;
+pub mod input;
 pub mod keypress;
 pub mod matcher;
+pub mod pipeline;
+pub mod recorder;
+pub mod ring;
 pub mod speaker;
+pub mod vad;
@@ -0,0 +1,478 @@
+use anyhow::Result;
+use log::debug;
+use nnnoiseless::DenoiseState;
+use rubato::{
+    Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+
+/// nnnoiseless 要求的采样率
+const DENOISE_SAMPLE_RATE: u32 = 48000;
+/// 送入 vosk 的采样率
+const OUTPUT_SAMPLE_RATE: u32 = 16000;
+/// 重采样时每次处理的输入采样数
+const RESAMPLE_CHUNK_SIZE: usize = 1024;
+
+/// 音频处理管线中的一个环节
+///
+/// 输入输出都是 i16 量级的单声道 f32 采样。环节内部可以缓存不足一帧的数据，
+/// 因此一次调用的输出长度不一定与输入对应。
+/// 实现应预先分配好内部缓冲区，稳定运行时 `process` 不再分配内存。
+/// 管线在启动识别时构建，再移交给音频处理线程，因此需要是 Send 的。
+pub trait AudioStage: Send {
+    fn name(&self) -> &'static str;
+    /// 处理一段音频，结果追加到 `output`
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()>;
+    /// 音频结束，输出内部缓存的全部数据，之后回到初始状态
+    fn flush(&mut self, _output: &mut Vec<f32>) -> Result<()> {
+        Ok(())
+    }
+    /// 丢弃内部缓存的数据，回到初始状态
+    fn reset(&mut self) {}
+}
+
+/// 重采样
+///
+/// 输出去掉了重采样器自身的延迟，`flush` 后的总输出长度为输入长度按采样率换算后的值。
+pub struct ResampleStage {
+    resampler: SincFixedIn<f32>,
+    ratio: f64,
+    chunk_size: usize,
+    buffer: Vec<f32>,
+    resampled: Vec<Vec<f32>>,
+    /// 开头还需丢弃的延迟采样数
+    delay: usize,
+    input_frames: u64,
+    output_frames: u64,
+}
+
+impl ResampleStage {
+    pub fn new(from: u32, to: u32, chunk_size: usize) -> Result<Self> {
+        let resampler = SincFixedIn::<f32>::new(
+            to as f64 / from as f64,
+            2.0,
+            SincInterpolationParameters {
+                sinc_len: 256,
+                f_cutoff: 0.95,
+                interpolation: SincInterpolationType::Linear,
+                oversampling_factor: 256,
+                window: WindowFunction::BlackmanHarris2,
+            },
+            chunk_size,
+            1,
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to create resampler {} -> {}: {}", from, to, e))?;
+
+        let resampled = resampler.output_buffer_allocate(true);
+        let delay = resampler.output_delay();
+        Ok(Self {
+            resampler,
+            ratio: to as f64 / from as f64,
+            chunk_size,
+            buffer: Vec::with_capacity(chunk_size * 2),
+            resampled,
+            delay,
+            input_frames: 0,
+            output_frames: 0,
+        })
+    }
+
+    /// 重采样缓存中所有完整的 chunk
+    fn resample_buffered(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        let mut consumed = 0;
+        while self.buffer.len() - consumed >= self.chunk_size {
+            let waves_in = [&self.buffer[consumed..consumed + self.chunk_size]];
+            let (frames_in, frames_out) = self
+                .resampler
+                .process_into_buffer(&waves_in, &mut self.resampled, None)
+                .map_err(|e| anyhow::anyhow!("Resampling error: {}", e))?;
+            consumed += frames_in;
+            let skip = self.delay.min(frames_out);
+            self.delay -= skip;
+            output.extend_from_slice(&self.resampled[0][skip..frames_out]);
+            self.output_frames += (frames_out - skip) as u64;
+        }
+        self.buffer.drain(..consumed);
+        Ok(())
+    }
+}
+
+impl AudioStage for ResampleStage {
+    fn name(&self) -> &'static str {
+        "resample"
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        self.input_frames += input.len() as u64;
+        self.buffer.extend_from_slice(input);
+        self.resample_buffered(output)
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        // 补零凑满 chunk，把缓存和重采样器延迟中的音频推出来，多出来的只是补的零
+        let expected = (self.input_frames as f64 * self.ratio).round() as u64;
+        let start = output.len();
+        while self.output_frames < expected {
+            self.buffer.resize(self.chunk_size, 0.0);
+            self.resample_buffered(output)?;
+        }
+        let extra = (self.output_frames - expected) as usize;
+        output.truncate(output.len() - extra.min(output.len() - start));
+        self.reset();
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.resampler.reset();
+        self.buffer.clear();
+        self.delay = self.resampler.output_delay();
+        self.input_frames = 0;
+        self.output_frames = 0;
+    }
+}
+
+/// 降噪，输入必须是 48kHz
+pub struct DenoiseStage {
+    denoiser: Box<DenoiseState<'static>>,
+    buffer: Vec<f32>,
+}
+
+impl DenoiseStage {
+    pub fn new() -> Self {
+        Self {
+            denoiser: DenoiseState::new(),
+            buffer: Vec::with_capacity(DenoiseState::FRAME_SIZE * 2),
+        }
+    }
+}
+
+impl Default for DenoiseStage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioStage for DenoiseStage {
+    fn name(&self) -> &'static str {
+        "denoise"
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        self.buffer.extend_from_slice(input);
+        let mut out_frame = [0.0f32; DenoiseState::FRAME_SIZE];
+        let mut consumed = 0;
+        for in_frame in self.buffer.chunks_exact(DenoiseState::FRAME_SIZE) {
+            let _ = self.denoiser.process_frame(&mut out_frame, in_frame);
+            output.extend_from_slice(&out_frame);
+            consumed += DenoiseState::FRAME_SIZE;
+        }
+        self.buffer.drain(..consumed);
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        // 最后不足一帧的音频补零处理，只输出原有的长度
+        let remaining = self.buffer.len();
+        if remaining > 0 {
+            self.buffer.resize(DenoiseState::FRAME_SIZE, 0.0);
+            let mut out_frame = [0.0f32; DenoiseState::FRAME_SIZE];
+            let _ = self.denoiser.process_frame(&mut out_frame, &self.buffer);
+            output.extend_from_slice(&out_frame[..remaining]);
+        }
+        self.reset();
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.denoiser = DenoiseState::new();
+        self.buffer.clear();
+    }
+}
+
+/// 由多个环节串联而成的音频处理管线
+///
+/// 设备采样率的音频先经过转换环节变为 16kHz (重采样、降噪)，再依次经过 `push` 追加的
+/// 处理环节 (增益、VAD 等)，输出送去识别。设备重新打开时只需重建转换环节。
+pub struct AudioPipeline {
+    enable_denoise: bool,
+    /// 设备采样率 -> 16kHz 的转换环节
+    conversion: Vec<Box<dyn AudioStage>>,
+    /// 16kHz 音频的处理环节
+    stages: Vec<Box<dyn AudioStage>>,
+    input: Vec<f32>,
+    output: Vec<f32>,
+}
+
+impl AudioPipeline {
+    /// 创建空管线，在 `set_sample_rate` 之前输入视为 16kHz
+    pub fn new(enable_denoise: bool) -> Self {
+        Self {
+            enable_denoise,
+            conversion: Vec::new(),
+            stages: Vec::new(),
+            input: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    /// 按设备采样率和是否降噪构建只有转换环节的管线
+    pub fn build(sample_rate: u32, enable_denoise: bool) -> Result<Self> {
+        let mut pipeline = Self::new(enable_denoise);
+        pipeline.set_sample_rate(sample_rate)?;
+        Ok(pipeline)
+    }
+
+    /// 按设备采样率重建转换环节，并丢弃所有环节中缓存的数据
+    ///
+    /// - 降噪: 设备采样率 -> 48k -> 降噪 -> 16k
+    /// - 不降噪: 设备采样率 -> 16k
+    pub fn set_sample_rate(&mut self, sample_rate: u32) -> Result<()> {
+        let mut conversion: Vec<Box<dyn AudioStage>> = Vec::new();
+        if self.enable_denoise {
+            if sample_rate != DENOISE_SAMPLE_RATE {
+                conversion.push(Box::new(ResampleStage::new(
+                    sample_rate,
+                    DENOISE_SAMPLE_RATE,
+                    RESAMPLE_CHUNK_SIZE,
+                )?));
+            }
+            conversion.push(Box::new(DenoiseStage::new()));
+            conversion.push(Box::new(ResampleStage::new(
+                DENOISE_SAMPLE_RATE,
+                OUTPUT_SAMPLE_RATE,
+                DenoiseState::FRAME_SIZE,
+            )?));
+        } else if sample_rate != OUTPUT_SAMPLE_RATE {
+            conversion.push(Box::new(ResampleStage::new(
+                sample_rate,
+                OUTPUT_SAMPLE_RATE,
+                RESAMPLE_CHUNK_SIZE,
+            )?));
+        }
+        self.conversion = conversion;
+        self.reset();
+        debug!(
+            "Audio pipeline ({} Hz): {:?}",
+            sample_rate,
+            self.stage_names()
+        );
+        Ok(())
+    }
+
+    /// 在管线末尾追加一个 16kHz 音频的处理环节
+    pub fn push<S: AudioStage + 'static>(&mut self, stage: S) {
+        self.stages.push(Box::new(stage));
+    }
+
+    /// 按顺序列出所有环节的名称
+    pub fn stage_names(&self) -> Vec<&'static str> {
+        self.conversion
+            .iter()
+            .chain(self.stages.iter())
+            .map(|s| s.name())
+            .collect()
+    }
+
+    /// 让一段音频依次经过所有环节，结果追加到 `output`
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        self.input.clear();
+        self.input.extend_from_slice(input);
+        for stage in self.conversion.iter_mut().chain(self.stages.iter_mut()) {
+            self.output.clear();
+            stage.process(&self.input, &mut self.output)?;
+            std::mem::swap(&mut self.input, &mut self.output);
+        }
+        output.extend_from_slice(&self.input);
+        Ok(())
+    }
+
+    /// 音频结束，依次推出各环节缓存的数据，结果追加到 `output`
+    pub fn flush(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        self.input.clear();
+        for stage in self.conversion.iter_mut().chain(self.stages.iter_mut()) {
+            self.output.clear();
+            stage.process(&self.input, &mut self.output)?;
+            stage.flush(&mut self.output)?;
+            std::mem::swap(&mut self.input, &mut self.output);
+        }
+        output.extend_from_slice(&self.input);
+        Ok(())
+    }
+
+    /// 丢弃所有环节中缓存的数据
+    pub fn reset(&mut self) {
+        for stage in self.conversion.iter_mut().chain(self.stages.iter_mut()) {
+            stage.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::event::EventEmitter;
+    use crate::core::gain::{GainStage, InputGain};
+
+    fn sine(sample_rate: u32, frequency: f32, samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                (t * frequency * std::f32::consts::TAU).sin() * 8000.0
+            })
+            .collect()
+    }
+
+    /// 按声卡回调的大小分块送入，最后 flush
+    fn run(stage: &mut dyn AudioStage, input: &[f32], block: usize) -> (Vec<f32>, usize) {
+        let mut output = Vec::new();
+        for samples in input.chunks(block) {
+            stage.process(samples, &mut output).unwrap();
+        }
+        let before_flush = output.len();
+        stage.flush(&mut output).unwrap();
+        (output, before_flush)
+    }
+
+    /// 用上升过零点估计频率
+    fn frequency(samples: &[f32], sample_rate: u32) -> f32 {
+        let crossings = samples
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        crossings as f32 * sample_rate as f32 / samples.len() as f32
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn resample_keeps_length_and_frequency() {
+        let input = sine(48000, 440.0, 48000);
+        let mut stage = ResampleStage::new(48000, 16000, RESAMPLE_CHUNK_SIZE).unwrap();
+        let (output, before_flush) = run(&mut stage, &input, 480);
+
+        assert!(before_flush < 16000);
+        assert_eq!(output.len(), 16000);
+        let f = frequency(&output[1600..14400], 16000);
+        assert!((f - 440.0).abs() < 5.0, "frequency {}", f);
+    }
+
+    #[test]
+    fn resample_flush_outputs_tail() {
+        // 1 秒静音后跟 50ms 的声音，声音全部在重采样器的缓存中
+        let mut input = vec![0.0; 44100];
+        input.extend(sine(44100, 440.0, 2205));
+        let mut stage = ResampleStage::new(44100, 16000, RESAMPLE_CHUNK_SIZE).unwrap();
+        let (output, _) = run(&mut stage, &input, 441);
+
+        assert_eq!(output.len(), 16800);
+        assert!(rms(&output[..15500]) < 50.0);
+        assert!(rms(&output[16100..16700]) > 4000.0);
+    }
+
+    #[test]
+    fn resample_restarts_after_flush() {
+        let input = sine(48000, 440.0, 4800);
+        let mut stage = ResampleStage::new(48000, 16000, RESAMPLE_CHUNK_SIZE).unwrap();
+        let (first, _) = run(&mut stage, &input, 480);
+        let (second, _) = run(&mut stage, &input, 480);
+        assert_eq!(first.len(), 1600);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn denoise_keeps_silence_and_flushes_partial_frame() {
+        let input = vec![0.0; 1000];
+        let mut stage = DenoiseStage::new();
+        let (output, before_flush) = run(&mut stage, &input, 100);
+
+        assert_eq!(before_flush, 2 * DenoiseState::FRAME_SIZE);
+        assert_eq!(output.len(), 1000);
+        assert!(output.iter().all(|s| s.abs() < 1.0));
+    }
+
+    #[test]
+    fn pipeline_without_conversion_passes_through() {
+        let input = sine(16000, 440.0, 1000);
+        let mut pipeline = AudioPipeline::build(16000, false).unwrap();
+        assert!(pipeline.stage_names().is_empty());
+
+        let mut output = Vec::new();
+        pipeline.process(&input, &mut output).unwrap();
+        pipeline.flush(&mut output).unwrap();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn pipeline_converts_to_16k() {
+        let input = sine(44100, 440.0, 44100);
+        let mut pipeline = AudioPipeline::build(44100, false).unwrap();
+        assert_eq!(pipeline.stage_names(), ["resample"]);
+
+        let mut output = Vec::new();
+        for samples in input.chunks(441) {
+            pipeline.process(samples, &mut output).unwrap();
+        }
+        pipeline.flush(&mut output).unwrap();
+        assert_eq!(output.len(), 16000);
+        let f = frequency(&output[1600..14400], 16000);
+        assert!((f - 440.0).abs() < 5.0, "frequency {}", f);
+    }
+
+    #[test]
+    fn pipeline_with_denoise_converts_to_16k() {
+        let input = sine(44100, 440.0, 44100);
+        let mut pipeline = AudioPipeline::build(44100, true).unwrap();
+        assert_eq!(pipeline.stage_names(), ["resample", "denoise", "resample"]);
+
+        let mut output = Vec::new();
+        for samples in input.chunks(441) {
+            pipeline.process(samples, &mut output).unwrap();
+        }
+        pipeline.flush(&mut output).unwrap();
+        assert_eq!(output.len(), 16000);
+    }
+
+    #[test]
+    fn pipeline_flushes_through_later_stages() {
+        // 重采样缓存的尾部要经过后面的增益环节，并把增益环节中不足一块的音频一起推出来
+        let input = sine(48000, 440.0, 48000);
+        let mut pipeline = AudioPipeline::build(48000, false).unwrap();
+        pipeline.push(GainStage::new(
+            InputGain::new(20.0 * 2f32.log10(), None, 0.0),
+            3200,
+            EventEmitter::new(),
+        ));
+        assert_eq!(pipeline.stage_names(), ["resample", "gain"]);
+
+        let mut output = Vec::new();
+        for samples in input.chunks(480) {
+            pipeline.process(samples, &mut output).unwrap();
+        }
+        assert_eq!(output.len() % 3200, 0);
+        pipeline.flush(&mut output).unwrap();
+        assert_eq!(output.len(), 16000);
+        let level = rms(&output[1600..14400]);
+        assert!(
+            (level - 8000.0 * 2.0 / 2f32.sqrt()).abs() < 200.0,
+            "rms {}",
+            level
+        );
+    }
+
+    #[test]
+    fn set_sample_rate_discards_buffered_audio() {
+        let mut pipeline = AudioPipeline::build(48000, false).unwrap();
+        let mut output = Vec::new();
+        pipeline
+            .process(&sine(48000, 440.0, 1000), &mut output)
+            .unwrap();
+
+        pipeline.set_sample_rate(16000).unwrap();
+        let input = sine(16000, 440.0, 100);
+        output.clear();
+        pipeline.process(&input, &mut output).unwrap();
+        pipeline.flush(&mut output).unwrap();
+        assert_eq!(output, input);
+    }
+}
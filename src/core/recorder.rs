@@ -0,0 +1,336 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::Serialize;
+
+use crate::core::matcher::MatchOutcome;
+
+/// 录音的采样率，与送入 vosk 的音频一致
+const RECORDING_SAMPLE_RATE: u32 = 16000;
+
+/// 识别过程中的一次中间结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PartialText {
+    /// 出现该结果时已送去识别的音频时长 (ms)
+    pub offset_ms: u64,
+    pub text: String,
+}
+
+/// 一次语音实际送去识别的音频 (16kHz，经过重采样、降噪和增益) 以及识别过程
+#[derive(Clone, Default)]
+pub struct UtteranceRecording {
+    /// 开始送去识别的时间 (Unix 毫秒)
+    pub started_at: u64,
+    pub samples: Vec<i16>,
+    pub partials: Vec<PartialText>,
+    /// 计算最终结果耗时 (ms)
+    pub recognize_ms: u64,
+}
+
+impl UtteranceRecording {
+    pub fn new() -> Self {
+        Self {
+            started_at: unix_millis(),
+            ..Default::default()
+        }
+    }
+
+    /// 记录中间结果，与上一次相同时忽略
+    pub fn push_partial(&mut self, text: &str) {
+        if text.is_empty() || self.partials.last().is_some_and(|p| p.text == text) {
+            return;
+        }
+        self.partials.push(PartialText {
+            offset_ms: self.duration(),
+            text: text.to_string(),
+        });
+    }
+
+    /// 录音时长 (ms)
+    pub fn duration(&self) -> u64 {
+        self.samples.len() as u64 * 1000 / RECORDING_SAMPLE_RATE as u64
+    }
+}
+
+impl fmt::Debug for UtteranceRecording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UtteranceRecording")
+            .field("started_at", &self.started_at)
+            .field("samples", &self.samples.len())
+            .field("partials", &self.partials)
+            .field("recognize_ms", &self.recognize_ms)
+            .finish()
+    }
+}
+
+/// 与 WAV 同名的 JSON 文件内容
+#[derive(Serialize)]
+struct RecordingMetadata<'a> {
+    started_at: u64,
+    duration_ms: u64,
+    recognize_ms: u64,
+    partials: &'a [PartialText],
+    text: &'a str,
+    #[serde(rename = "match")]
+    outcome: &'a MatchOutcome,
+}
+
+struct SaveRequest {
+    recording: Arc<UtteranceRecording>,
+    text: String,
+    outcome: MatchOutcome,
+}
+
+/// 调试用的录音器
+///
+/// 将每次最终识别的音频保存为 WAV，并在同名 JSON 中记录中间结果、最终结果、
+/// 匹配结果和耗时。只保留最近 `max_recordings` 条。
+/// 写文件和清理旧录音在单独的线程中进行，不阻塞音频处理线程。
+pub struct UtteranceRecorder {
+    tx: Sender<SaveRequest>,
+    _thread_handle: JoinHandle<()>,
+}
+
+impl UtteranceRecorder {
+    pub fn new(dir: impl Into<PathBuf>, max_recordings: usize) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create recording dir {}", dir.display()))?;
+        let mut writer = RecordingWriter::new(dir, max_recordings);
+
+        let (tx, rx) = std::sync::mpsc::channel::<SaveRequest>();
+        let handle = std::thread::spawn(move || {
+            for request in rx {
+                if let Err(e) = writer.save(&request.recording, &request.text, &request.outcome) {
+                    warn!("Failed to save recording: {:#}", e);
+                }
+            }
+        });
+        Ok(Self {
+            tx,
+            _thread_handle: handle,
+        })
+    }
+
+    /// 提交一条录音，由写入线程保存
+    pub fn save(
+        &self,
+        recording: Arc<UtteranceRecording>,
+        text: String,
+        outcome: MatchOutcome,
+    ) -> Result<()> {
+        self.tx
+            .send(SaveRequest {
+                recording,
+                text,
+                outcome,
+            })
+            .context("recorder thread has exited")
+    }
+}
+
+/// 写入线程的状态
+struct RecordingWriter {
+    dir: PathBuf,
+    max_recordings: usize,
+    /// 文件名中的序号，同一毫秒内开始的录音不会互相覆盖
+    next_seq: u64,
+}
+
+impl RecordingWriter {
+    fn new(dir: PathBuf, max_recordings: usize) -> Self {
+        Self {
+            dir,
+            max_recordings,
+            next_seq: 0,
+        }
+    }
+
+    fn save(
+        &mut self,
+        recording: &UtteranceRecording,
+        text: &str,
+        outcome: &MatchOutcome,
+    ) -> Result<()> {
+        let name = format!("utterance-{}-{:06}", recording.started_at, self.next_seq);
+        self.next_seq += 1;
+        let wav_path = self.dir.join(format!("{}.wav", name));
+        let json_path = self.dir.join(format!("{}.json", name));
+
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: RECORDING_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&wav_path, spec)
+            .with_context(|| format!("Failed to create {}", wav_path.display()))?;
+        for &sample in &recording.samples {
+            writer.write_sample(sample)?;
+        }
+        writer.finalize()?;
+
+        let metadata = RecordingMetadata {
+            started_at: recording.started_at,
+            duration_ms: recording.duration(),
+            recognize_ms: recording.recognize_ms,
+            partials: &recording.partials,
+            text,
+            outcome,
+        };
+        fs::write(&json_path, serde_json::to_vec_pretty(&metadata)?)
+            .with_context(|| format!("Failed to write {}", json_path.display()))?;
+        debug!("recording saved: {}", wav_path.display());
+
+        self.enforce_retention();
+        Ok(())
+    }
+
+    /// 删除超出数量限制的最早录音
+    fn enforce_retention(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut names = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let stem = name.strip_suffix(".wav")?;
+                Some((parse_stem(stem)?, stem.to_string()))
+            })
+            .collect::<Vec<_>>();
+        if names.len() <= self.max_recordings {
+            return;
+        }
+
+        // 按开始时间和序号排序，不依赖文件名的字符串顺序
+        names.sort();
+        for (_, stem) in &names[..names.len() - self.max_recordings] {
+            for ext in ["wav", "json"] {
+                let path = self.dir.join(format!("{}.{}", stem, ext));
+                if let Err(e) = remove_if_exists(&path) {
+                    warn!("Failed to remove old recording {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+/// 从录音文件名中解析 (开始时间, 序号)，兼容不带序号的旧文件名
+fn parse_stem(stem: &str) -> Option<(u64, u64)> {
+    let rest = stem.strip_prefix("utterance-")?;
+    match rest.split_once('-') {
+        Some((started_at, seq)) => Some((started_at.parse().ok()?, seq.parse().ok()?)),
+        None => Some((rest.parse().ok()?, 0)),
+    }
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// When UtteranceRecorder is dropped, tx is dropped first (field order), which closes
+// the channel. The writer thread saves the recordings still queued and then exits.
+// We don't join here so that stopping the audio thread is not held up by disk writes.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hellcall-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn recording(started_at: u64) -> UtteranceRecording {
+        UtteranceRecording {
+            started_at,
+            samples: vec![0; 160],
+            ..Default::default()
+        }
+    }
+
+    fn stems(dir: &Path, ext: &str) -> Vec<String> {
+        let mut stems = fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| {
+                let name = entry.unwrap().file_name().into_string().unwrap();
+                name.strip_suffix(&format!(".{}", ext)).map(str::to_string)
+            })
+            .collect::<Vec<_>>();
+        stems.sort();
+        stems
+    }
+
+    #[test]
+    fn same_millisecond_does_not_overwrite() {
+        let dir = temp_dir("recorder-same-ms");
+        let mut writer = RecordingWriter::new(dir.clone(), 10);
+        for _ in 0..3 {
+            writer
+                .save(&recording(1000), "", &MatchOutcome::Empty)
+                .unwrap();
+        }
+        assert_eq!(
+            stems(&dir, "wav"),
+            [
+                "utterance-1000-000000",
+                "utterance-1000-000001",
+                "utterance-1000-000002"
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn retention_removes_oldest_by_time() {
+        let dir = temp_dir("recorder-retention");
+        // 旧格式的文件名同样参与清理，时间戳位数不同时也按数值排序
+        fs::write(dir.join("utterance-999.wav"), b"").unwrap();
+        fs::write(dir.join("utterance-999.json"), b"").unwrap();
+        fs::write(dir.join("notes.wav"), b"").unwrap();
+
+        let mut writer = RecordingWriter::new(dir.clone(), 2);
+        for started_at in [3000, 1000, 2000] {
+            writer
+                .save(&recording(started_at), "", &MatchOutcome::Empty)
+                .unwrap();
+        }
+        assert_eq!(
+            stems(&dir, "wav"),
+            ["notes", "utterance-2000-000002", "utterance-3000-000000"]
+        );
+        assert_eq!(
+            stems(&dir, "json"),
+            ["utterance-2000-000002", "utterance-3000-000000"]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parses_recording_names() {
+        assert_eq!(parse_stem("utterance-1000-000002"), Some((1000, 2)));
+        assert_eq!(parse_stem("utterance-1000"), Some((1000, 0)));
+        assert_eq!(parse_stem("utterance-x"), None);
+        assert_eq!(parse_stem("notes"), None);
+    }
+}
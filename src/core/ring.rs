@@ -0,0 +1,127 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// 单生产者单消费者的无锁采样环形缓冲区
+///
+/// 生产者在音频回调中写入，消费者在处理线程中读取，双方都不会阻塞。
+/// 采样以 f32 的位模式存放在 AtomicU32 中，无需 unsafe。
+struct Shared {
+    buffer: Box<[AtomicU32]>,
+    mask: usize,
+    /// 已写入的采样总数
+    head: AtomicUsize,
+    /// 已读取的采样总数
+    tail: AtomicUsize,
+}
+
+pub struct RingProducer {
+    shared: Arc<Shared>,
+}
+
+pub struct RingConsumer {
+    shared: Arc<Shared>,
+}
+
+/// 创建容量至少为 `capacity` 的环形缓冲区
+pub fn sample_ring(capacity: usize) -> (RingProducer, RingConsumer) {
+    let capacity = capacity.max(1).next_power_of_two();
+    let shared = Arc::new(Shared {
+        buffer: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+        mask: capacity - 1,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+    (
+        RingProducer {
+            shared: Arc::clone(&shared),
+        },
+        RingConsumer { shared },
+    )
+}
+
+impl RingProducer {
+    /// 写入采样，空间不足时丢弃放不下的部分，返回丢弃的采样数
+    pub fn push<I>(&mut self, samples: I) -> usize
+    where
+        I: ExactSizeIterator<Item = f32>,
+    {
+        let shared = &self.shared;
+        let head = shared.head.load(Ordering::Relaxed);
+        let tail = shared.tail.load(Ordering::Acquire);
+        let free = shared.buffer.len() - head.wrapping_sub(tail);
+
+        let len = samples.len();
+        let mut written = 0;
+        for sample in samples.take(free) {
+            shared.buffer[head.wrapping_add(written) & shared.mask]
+                .store(sample.to_bits(), Ordering::Relaxed);
+            written += 1;
+        }
+        shared
+            .head
+            .store(head.wrapping_add(written), Ordering::Release);
+        len - written
+    }
+}
+
+impl RingConsumer {
+    /// 取出当前所有可读的采样，追加到 `output`，返回取出的采样数
+    pub fn pop_into(&mut self, output: &mut Vec<f32>) -> usize {
+        let shared = &self.shared;
+        let tail = shared.tail.load(Ordering::Relaxed);
+        let head = shared.head.load(Ordering::Acquire);
+        let available = head.wrapping_sub(tail);
+
+        output.reserve(available);
+        for i in 0..available {
+            let bits = shared.buffer[tail.wrapping_add(i) & shared.mask].load(Ordering::Relaxed);
+            output.push(f32::from_bits(bits));
+        }
+        shared.tail.store(head, Ordering::Release);
+        available
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pop_into_keeps_order() {
+        let (mut producer, mut consumer) = sample_ring(8);
+        assert_eq!(producer.push([1.0, 2.0, 3.0].into_iter()), 0);
+        assert_eq!(producer.push([4.0, 5.0].into_iter()), 0);
+
+        let mut output = vec![0.0];
+        assert_eq!(consumer.pop_into(&mut output), 5);
+        assert_eq!(output, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(consumer.pop_into(&mut output), 0);
+    }
+
+    #[test]
+    fn test_push_returns_dropped_count() {
+        let (mut producer, mut consumer) = sample_ring(4);
+        assert_eq!(producer.push([1.0, 2.0, 3.0].into_iter()), 0);
+        assert_eq!(producer.push([4.0, 5.0, 6.0].into_iter()), 2);
+        assert_eq!(producer.push([7.0].into_iter()), 1);
+
+        let mut output = Vec::new();
+        consumer.pop_into(&mut output);
+        assert_eq!(output, [1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_wraparound() {
+        let (mut producer, mut consumer) = sample_ring(4);
+        let mut output = Vec::new();
+        let mut next = 0.0;
+        for _ in 0..10 {
+            let samples = [next, next + 1.0, next + 2.0];
+            next += 3.0;
+            assert_eq!(producer.push(samples.into_iter()), 0);
+            output.clear();
+            assert_eq!(consumer.pop_into(&mut output), 3);
+            assert_eq!(output, samples);
+        }
+    }
+}
@@ -1,22 +1,105 @@
-use anyhow::{Context, Result};
-use log::{info, warn};
-use rodio::{OutputStream, OutputStreamBuilder};
+use anyhow::{Context, Result, anyhow};
+use cpal::traits::HostTrait;
+use log::{debug, info, warn};
+use rand::seq::IndexedRandom;
+use rodio::{Decoder, OutputStream, OutputStreamBuilder, Sink};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::fs::File;
 use std::io::BufReader;
-use std::path::Path;
-use std::sync::mpsc::Sender;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{RecvTimeoutError, Sender};
 use std::thread::JoinHandle;
+use std::time::Duration;
 
+use crate::core::device::{find_output_device, get_host, list_output_device_ids};
+use crate::core::event::{EventEmitter, HellcallEvent};
+
+/// 默认的音效目录，相对于当前目录
+pub const AUDIO_DIR: &str = "audio";
+
+/// 有音效正在播放时检查播放是否结束的间隔
+const PLAYBACK_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 正在播放时又有新音效要播放的处理方式
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaybackPolicy {
+    /// 排队依次播放，队列满时丢弃最早排队的音效
+    #[serde(rename = "queue")]
+    #[default]
+    Queue,
+    /// 停止正在播放的音效，立即播放新音效
+    #[serde(rename = "interrupt")]
+    Interrupt,
+    /// 正在播放时丢弃新音效
+    #[serde(rename = "drop_if_busy")]
+    DropIfBusy,
+    /// 与正在播放的音效叠加播放
+    #[serde(rename = "mix")]
+    Mix,
+}
+
+/// Speaker 的全局设置
+#[derive(Debug, Clone)]
+pub struct SpeakerOptions {
+    /// 音频后端，不指定时使用系统默认后端
+    pub host: Option<String>,
+    /// 输出设备 ID 或名称，不指定时使用系统默认设备
+    pub output: Option<String>,
+    /// 音量，1.0 为原始音量
+    pub volume: f32,
+    /// 播放速度，1.0 为原始速度
+    pub speed: f32,
+    /// 正在播放时又有新音效要播放的处理方式
+    pub policy: PlaybackPolicy,
+    /// `Queue` 策略下最多排队等待的音效数，不含正在播放的音效
+    pub max_queue: usize,
+}
+
+impl Default for SpeakerOptions {
+    fn default() -> Self {
+        Self {
+            host: None,
+            output: None,
+            volume: 1.7,
+            speed: 1.05,
+            policy: PlaybackPolicy::Queue,
+            max_queue: 3,
+        }
+    }
+}
+
+/// 单次播放的设置，未指定的项使用 Speaker 的全局设置
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackOptions {
+    pub output: Option<String>,
+    pub volume: Option<f32>,
+    pub speed: Option<f32>,
+}
+
+struct PlayRequest {
+    path: PathBuf,
+    options: PlaybackOptions,
+}
+
+/// 音效播放器，支持 WAV、OGG (Vorbis)、MP3 和 FLAC
+///
+/// 单个音效打开或解码失败只会跳过该音效，并发送 `AudioPlaybackFailed` 事件。
 pub struct Speaker {
-    tx: Sender<String>,
-    _thread_handle: JoinHandle<Result<()>>,
+    tx: Sender<PlayRequest>,
+    _thread_handle: JoinHandle<()>,
 }
 
 impl Speaker {
+    /// 使用默认设置在系统默认输出设备上播放
     pub fn new() -> Result<Self> {
-        let stream_handle =
-            OutputStreamBuilder::open_default_stream().context("open default stream failed")?;
-        let (tx, handle) = Self::init_thread(stream_handle);
+        Self::with_options(SpeakerOptions::default(), EventEmitter::new())
+    }
+
+    pub fn with_options(options: SpeakerOptions, events: EventEmitter) -> Result<Self> {
+        let host = get_host(options.host.as_deref())?;
+        let stream_handle = open_output_stream(&host, options.output.as_deref())?;
+        let (tx, handle) = Self::init_thread(host, stream_handle, options, events);
 
         Ok(Self {
             tx,
@@ -24,35 +107,445 @@ impl Speaker {
         })
     }
 
-    fn init_thread(stream_handle: OutputStream) -> (Sender<String>, JoinHandle<Result<()>>) {
-        let (tx, rx) = std::sync::mpsc::channel::<String>();
-        let handle = std::thread::spawn(move || -> Result<()> {
-            while let Ok(audio_path) = rx.recv() {
-                let file = BufReader::new(File::open(&audio_path).context("open file failed")?);
-                info!("play audio: {}", &audio_path);
-                let sink = rodio::play(stream_handle.mixer(), file).context("play wav failed")?;
-                sink.set_volume(1.7);
-                sink.set_speed(1.05);
-                sink.sleep_until_end();
+    fn init_thread(
+        host: cpal::Host,
+        stream_handle: OutputStream,
+        options: SpeakerOptions,
+        events: EventEmitter,
+    ) -> (Sender<PlayRequest>, JoinHandle<()>) {
+        let (tx, rx) = std::sync::mpsc::channel::<PlayRequest>();
+        let handle = std::thread::spawn(move || {
+            let mut player = Player::new(host, stream_handle, options, events);
+            loop {
+                // 空闲时阻塞等待，播放中定时检查是否播完以便播放排队的音效
+                let request = if player.is_idle() {
+                    match rx.recv() {
+                        Ok(request) => Some(request),
+                        Err(_) => break,
+                    }
+                } else {
+                    match rx.recv_timeout(PLAYBACK_POLL_INTERVAL) {
+                        Ok(request) => Some(request),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                };
+
+                if let Some(request) = request {
+                    player.submit(request);
+                }
+                player.update();
             }
 
-            Ok(())
+            player.finish();
         });
         (tx, handle)
     }
 
+    /// 播放音效，文件不存在时只记录警告
+    #[deprecated(note = "use `play_audio` instead")]
     pub fn play_wav(&self, path: &str) -> Result<()> {
         if Path::new(path).exists() {
-            self.tx.send(path.to_string())?;
+            self.play_audio(path)?;
         } else {
             warn!("audio file not found: {}", path)
         }
         Ok(())
     }
+
+    pub fn play_audio(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.play_audio_with(path, PlaybackOptions::default())
+    }
+
+    /// 按指定的设置播放
+    pub fn play_audio_with(&self, path: impl AsRef<Path>, options: PlaybackOptions) -> Result<()> {
+        self.tx
+            .send(PlayRequest {
+                path: path.as_ref().to_path_buf(),
+                options,
+            })
+            .context("speaker thread has exited")
+    }
+}
+
+/// 按播放策略决定新音效立即播放、排队还是丢弃，不涉及实际播放
+struct PlaybackQueue<T> {
+    policy: PlaybackPolicy,
+    max_queue: usize,
+    /// 排队等待播放的音效
+    queue: VecDeque<T>,
+}
+
+/// [`PlaybackQueue::submit`] 的处理结果
+#[derive(Debug, PartialEq)]
+enum Submit<T> {
+    /// 立即播放，`interrupt` 为 true 时先停止正在播放的音效
+    Play { request: T, interrupt: bool },
+    /// 已排队，队列已满时 `dropped` 是被挤掉的最早排队的音效
+    Queued { dropped: Option<T> },
+    /// 正在播放，新音效被丢弃
+    Dropped(T),
+}
+
+impl<T> PlaybackQueue<T> {
+    fn new(policy: PlaybackPolicy, max_queue: usize) -> Self {
+        Self {
+            policy,
+            max_queue,
+            queue: VecDeque::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// 按播放策略处理新音效，`busy` 表示是否有音效正在播放
+    fn submit(&mut self, request: T, busy: bool) -> Submit<T> {
+        match self.policy {
+            PlaybackPolicy::Queue => {
+                let dropped = if self.queue.len() >= self.max_queue.max(1) {
+                    self.queue.pop_front()
+                } else {
+                    None
+                };
+                self.queue.push_back(request);
+                Submit::Queued { dropped }
+            }
+            PlaybackPolicy::Interrupt => Submit::Play {
+                request,
+                interrupt: true,
+            },
+            PlaybackPolicy::DropIfBusy if busy => Submit::Dropped(request),
+            PlaybackPolicy::DropIfBusy | PlaybackPolicy::Mix => Submit::Play {
+                request,
+                interrupt: false,
+            },
+        }
+    }
+
+    /// 取出下一个排队的音效
+    fn pop(&mut self) -> Option<T> {
+        self.queue.pop_front()
+    }
+}
+
+/// 播放线程的状态
+struct Player {
+    host: cpal::Host,
+    stream_handle: OutputStream,
+    /// 指令单独指定的输出设备，首次播放时打开
+    streams: HashMap<String, OutputStream>,
+    options: SpeakerOptions,
+    /// 正在播放的音效，只有 `Mix` 策略下会有多个
+    playing: Vec<Sink>,
+    queue: PlaybackQueue<PlayRequest>,
+    events: EventEmitter,
+}
+
+impl Player {
+    fn new(
+        host: cpal::Host,
+        stream_handle: OutputStream,
+        options: SpeakerOptions,
+        events: EventEmitter,
+    ) -> Self {
+        Self {
+            host,
+            stream_handle,
+            streams: HashMap::new(),
+            queue: PlaybackQueue::new(options.policy, options.max_queue),
+            options,
+            playing: Vec::new(),
+            events,
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.playing.is_empty() && self.queue.is_empty()
+    }
+
+    fn is_busy(&mut self) -> bool {
+        self.playing.retain(|sink| !sink.empty());
+        !self.playing.is_empty()
+    }
+
+    /// 按播放策略处理新音效
+    fn submit(&mut self, request: PlayRequest) {
+        let busy = self.is_busy();
+        match self.queue.submit(request, busy) {
+            Submit::Play { request, interrupt } => {
+                if interrupt {
+                    for sink in self.playing.drain(..) {
+                        sink.stop();
+                    }
+                }
+                self.play(request);
+            }
+            Submit::Queued {
+                dropped: Some(dropped),
+            } => debug!(
+                "playback queue is full, drop audio: {}",
+                dropped.path.display()
+            ),
+            Submit::Queued { dropped: None } => {}
+            Submit::Dropped(request) => {
+                debug!("speaker is busy, drop audio: {}", request.path.display())
+            }
+        }
+    }
+
+    /// 清理播完的音效，空闲时播放排队的下一个音效
+    fn update(&mut self) {
+        // 排队的音效播放失败时继续尝试下一个
+        while !self.is_busy()
+            && let Some(request) = self.queue.pop()
+        {
+            self.play(request);
+        }
+    }
+
+    /// 播放音效，失败时跳过该音效并发送事件
+    fn play(&mut self, request: PlayRequest) {
+        if let Err(e) = self.try_play(&request) {
+            let error = format!("{:#}", e);
+            warn!("Failed to play {}: {}", request.path.display(), error);
+            self.events.emit(HellcallEvent::AudioPlaybackFailed {
+                path: request.path.display().to_string(),
+                error,
+            });
+        }
+    }
+
+    fn try_play(&mut self, request: &PlayRequest) -> Result<()> {
+        let play = &request.options;
+        let mut mixer = self.stream_handle.mixer();
+        if let Some(output) = play.output.as_deref().filter(|x| !x.is_empty())
+            && self.options.output.as_deref() != Some(output)
+        {
+            if !self.streams.contains_key(output) {
+                match open_output_stream(&self.host, Some(output)) {
+                    Ok(stream) => {
+                        self.streams.insert(output.to_string(), stream);
+                    }
+                    Err(e) => warn!("{:#}, fall back to the default output", e),
+                }
+            }
+            if let Some(stream) = self.streams.get(output) {
+                mixer = stream.mixer();
+            }
+        }
+
+        let source = open_audio(&request.path)?;
+        info!("play audio: {}", request.path.display());
+        let sink = Sink::connect_new(mixer);
+        sink.append(source);
+        sink.set_volume(play.volume.unwrap_or(self.options.volume));
+        sink.set_speed(play.speed.unwrap_or(self.options.speed));
+        self.playing.push(sink);
+        Ok(())
+    }
+
+    /// 等正在播放的音效播完，丢弃排队的音效
+    fn finish(self) {
+        for sink in &self.playing {
+            sink.sleep_until_end();
+        }
+    }
+}
+
+/// 将音效目录解析为绝对路径，不指定时使用 [`AUDIO_DIR`]
+///
+/// 启动时解析一次，播放时不再依赖当前目录。
+pub fn resolve_audio_dir(dir: Option<&str>) -> Result<PathBuf> {
+    let dir = dir.filter(|d| !d.is_empty()).unwrap_or(AUDIO_DIR);
+    Ok(std::env::current_dir()?.join(dir))
+}
+
+/// 从 `audio_files` 中随机选择一个音效播放
+pub fn play_random_audio(
+    speaker: &Speaker,
+    audio_dir: &Path,
+    audio_files: &[String],
+    options: &PlaybackOptions,
+) {
+    if let Some(audio_path) = audio_files.choose(&mut rand::rng()) {
+        let _ = speaker.play_audio_with(audio_dir.join(audio_path), options.clone());
+    }
+}
+
+/// 打开音频文件，按扩展名提示解码器
+fn open_audio(path: &Path) -> Result<Decoder<BufReader<File>>> {
+    let file = File::open(path).context("open file failed")?;
+    let byte_len = file.metadata().context("open file failed")?.len();
+    let mut builder = Decoder::builder()
+        .with_data(BufReader::new(file))
+        .with_byte_len(byte_len)
+        .with_seekable(true);
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        builder = builder.with_hint(ext);
+    }
+    builder.build().context("decode audio failed")
+}
+
+/// 检查音效文件能否正常解码
+pub fn validate_audio(path: &Path) -> Result<()> {
+    let mut source = open_audio(path)?;
+    if source.next().is_none() {
+        return Err(anyhow!("audio file is empty"));
+    }
+    Ok(())
+}
+
+/// 检查 `dir` 下的所有音效文件，记录无法播放的文件并返回它们
+pub fn validate_audio_files<'a>(
+    dir: &Path,
+    files: impl IntoIterator<Item = &'a String>,
+) -> BTreeSet<String> {
+    files
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|file| match validate_audio(&dir.join(file)) {
+            Ok(()) => false,
+            Err(e) => {
+                warn!(
+                    "Invalid audio file {}, skipped: {:#}",
+                    dir.join(file).display(),
+                    e
+                );
+                true
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+/// 打开输出设备，不指定时使用系统默认设备
+fn open_output_stream(host: &cpal::Host, output: Option<&str>) -> Result<OutputStream> {
+    let device = match output.filter(|x| !x.is_empty()) {
+        Some(output) => find_output_device(host, output).with_context(|| {
+            format!(
+                "Output device '{}' not found, available devices: {:?}",
+                output,
+                list_output_device_ids(host)
+            )
+        })?,
+        None => host
+            .default_output_device()
+            .context("No default output device found")?,
+    };
+    OutputStreamBuilder::from_device(device)
+        .and_then(|builder| builder.open_stream_or_fallback())
+        .context("open output stream failed")
 }
 
 // When Speaker is dropped, tx is dropped first (field order), which closes the
-// mpsc channel. The thread's rx.recv() will then return Err and the thread exits.
+// mpsc channel. The playback thread then leaves its loop, drops the queued clips
+// and waits for the clips already playing to finish before exiting.
 // _thread_handle is dropped last, but we don't join here because audio playback
-// may still be in progress (sink.sleep_until_end()). The thread will exit naturally
-// after the current audio clip finishes.
+// may still be in progress. The thread will exit naturally after that.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(request: u32, interrupt: bool) -> Submit<u32> {
+        Submit::Play { request, interrupt }
+    }
+
+    fn drain(queue: &mut PlaybackQueue<u32>) -> Vec<u32> {
+        std::iter::from_fn(|| queue.pop()).collect()
+    }
+
+    #[test]
+    fn queue_plays_in_order() {
+        let mut queue = PlaybackQueue::new(PlaybackPolicy::Queue, 3);
+        for request in 1..=3 {
+            assert_eq!(
+                queue.submit(request, true),
+                Submit::Queued { dropped: None }
+            );
+        }
+        assert_eq!(drain(&mut queue), [1, 2, 3]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn full_queue_evicts_oldest() {
+        let mut queue = PlaybackQueue::new(PlaybackPolicy::Queue, 2);
+        queue.submit(1, true);
+        queue.submit(2, true);
+        assert_eq!(queue.submit(3, true), Submit::Queued { dropped: Some(1) });
+        assert_eq!(drain(&mut queue), [2, 3]);
+    }
+
+    #[test]
+    fn zero_max_queue_keeps_one() {
+        let mut queue = PlaybackQueue::new(PlaybackPolicy::Queue, 0);
+        queue.submit(1, true);
+        assert_eq!(queue.submit(2, true), Submit::Queued { dropped: Some(1) });
+        assert_eq!(drain(&mut queue), [2]);
+    }
+
+    #[test]
+    fn interrupt_always_plays() {
+        let mut queue = PlaybackQueue::new(PlaybackPolicy::Interrupt, 3);
+        assert_eq!(queue.submit(1, false), play(1, true));
+        assert_eq!(queue.submit(2, true), play(2, true));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn drop_if_busy_drops_only_when_busy() {
+        let mut queue = PlaybackQueue::new(PlaybackPolicy::DropIfBusy, 3);
+        assert_eq!(queue.submit(1, false), play(1, false));
+        assert_eq!(queue.submit(2, true), Submit::Dropped(2));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn invalid_audio_files_are_reported() {
+        let dir = std::env::temp_dir().join(format!("hellcall-audio-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(dir.join("ok.wav"), spec).unwrap();
+        for _ in 0..160 {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        hound::WavWriter::create(dir.join("empty.wav"), spec)
+            .unwrap()
+            .finalize()
+            .unwrap();
+        std::fs::write(dir.join("garbage.wav"), b"not audio").unwrap();
+
+        let files = [
+            "ok.wav",
+            "empty.wav",
+            "garbage.wav",
+            "missing.wav",
+            "ok.wav",
+        ]
+        .map(String::from);
+        let invalid = validate_audio_files(&dir, &files);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            invalid.into_iter().collect::<Vec<_>>(),
+            ["empty.wav", "garbage.wav", "missing.wav"]
+        );
+    }
+
+    #[test]
+    fn mix_plays_alongside() {
+        let mut queue = PlaybackQueue::new(PlaybackPolicy::Mix, 3);
+        assert_eq!(queue.submit(1, false), play(1, false));
+        assert_eq!(queue.submit(2, true), play(2, false));
+        assert!(queue.is_empty());
+    }
+}
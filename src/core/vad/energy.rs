@@ -0,0 +1,105 @@
+use anyhow::Result;
+
+use super::VoiceDetector;
+
+/// 底噪估计的下限，避免完全静音时任何杂音都超过门限
+const MIN_NOISE_FLOOR: f32 = 50.0;
+/// 当前音量低于底噪时，底噪向下跟随的速度
+const FALL_RATE: f32 = 0.1;
+/// 非人声帧时，底噪向上跟随的速度
+const RISE_RATE: f32 = 0.01;
+/// 人声帧时底噪也缓慢上升，防止环境噪音持续变大后门限一直打开
+const VOICE_RISE_RATE: f32 = 0.001;
+
+/// 基于音量的语音活动检测
+///
+/// 帧的 RMS 超过底噪的 `ratio` 倍即认为是人声，底噪随环境噪音自适应调整。
+pub struct EnergyVad {
+    ratio: f32,
+    noise_floor: f32,
+    frame_samples: usize,
+}
+
+impl EnergyVad {
+    /// `ratio` 为人声相对底噪的倍数，`frame_length` 为每帧时长 (ms)
+    pub fn new(ratio: f32, frame_length: u64) -> Self {
+        Self {
+            ratio,
+            noise_floor: MIN_NOISE_FLOOR,
+            frame_samples: 16000 * frame_length as usize / 1000,
+        }
+    }
+
+    fn rms(frame: &[i16]) -> f32 {
+        if frame.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
+        (sum / frame.len() as f64).sqrt() as f32
+    }
+}
+
+impl VoiceDetector for EnergyVad {
+    fn frame_samples(&self) -> usize {
+        self.frame_samples
+    }
+
+    fn is_voice(&mut self, frame: &[i16]) -> Result<bool> {
+        let rms = Self::rms(frame);
+        let is_voice = rms > self.noise_floor * self.ratio;
+
+        let rate = if rms < self.noise_floor {
+            FALL_RATE
+        } else if is_voice {
+            VOICE_RISE_RATE
+        } else {
+            RISE_RATE
+        };
+        self.noise_floor += (rms - self.noise_floor) * rate;
+        self.noise_floor = self.noise_floor.max(MIN_NOISE_FLOOR);
+
+        Ok(is_voice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 幅度恒定的方波，RMS 等于 `amplitude`
+    fn frame(amplitude: i16) -> Vec<i16> {
+        (0..320)
+            .map(|i| if i % 2 == 0 { amplitude } else { -amplitude })
+            .collect()
+    }
+
+    #[test]
+    fn detects_voice_above_noise_floor() {
+        let mut vad = EnergyVad::new(3.0, 20);
+        assert_eq!(vad.frame_samples(), 320);
+        for _ in 0..50 {
+            assert!(!vad.is_voice(&frame(40)).unwrap());
+        }
+        assert!(vad.is_voice(&frame(2000)).unwrap());
+        assert!(!vad.is_voice(&frame(100)).unwrap());
+    }
+
+    #[test]
+    fn noise_floor_follows_steady_noise() {
+        let mut vad = EnergyVad::new(3.0, 20);
+        // 持续变大的环境噪音一开始会被当作人声，底噪跟上后门限关闭
+        assert!(vad.is_voice(&frame(500)).unwrap());
+        for _ in 0..2000 {
+            vad.is_voice(&frame(500)).unwrap();
+        }
+        assert!(!vad.is_voice(&frame(500)).unwrap());
+        assert!(vad.is_voice(&frame(3000)).unwrap());
+
+        // 噪音消失后底噪迅速回落
+        for _ in 0..100 {
+            vad.is_voice(&frame(0)).unwrap();
+        }
+        assert_eq!(vad.noise_floor, MIN_NOISE_FLOOR);
+        assert!(vad.is_voice(&frame(500)).unwrap());
+    }
+}
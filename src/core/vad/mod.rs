@@ -0,0 +1,199 @@
+pub mod energy;
+#[cfg(feature = "silero-vad")]
+pub mod silero;
+pub mod webrtc;
+
+pub use energy::*;
+#[cfg(feature = "silero-vad")]
+pub use silero::*;
+pub use webrtc::*;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::core::pipeline::AudioStage;
+
+/// 语音活动检测器，逐帧判断 16kHz 单声道音频中是否有人声
+///
+/// 连续多少帧才算开始说话/进入静音由 `AudioRecognizer` 统一处理，检测器只负责单帧判断。
+/// 检测器在启动识别时创建，再移交给音频处理线程，因此需要是 Send 的。
+pub trait VoiceDetector: Send {
+    /// 每帧的采样数
+    fn frame_samples(&self) -> usize;
+
+    /// 判断一帧音频是否为人声
+    fn is_voice(&mut self, frame: &[i16]) -> Result<bool>;
+}
+
+/// Silero VAD 模型在 16kHz 下每帧的采样数，不受 `vad_frame_length` 影响
+pub const SILERO_FRAME_SAMPLES: usize = 512;
+
+/// 语音活动检测的实现
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VadEngine {
+    /// WebRTC VAD
+    #[serde(rename = "webrtc")]
+    #[default]
+    WebRtc,
+    /// 基于音量的门限，噪音底噪自适应
+    #[serde(rename = "energy")]
+    Energy,
+    /// Silero VAD 神经网络模型，需要启用 `silero-vad` feature
+    #[serde(rename = "silero")]
+    Silero,
+}
+
+impl VadEngine {
+    /// 每帧的采样数，`frame_length` 为配置的帧长 (ms)，Silero 的帧长是固定的
+    pub fn frame_samples(&self, frame_length: u64) -> usize {
+        match self {
+            VadEngine::Silero => SILERO_FRAME_SAMPLES,
+            VadEngine::WebRtc | VadEngine::Energy => 16000 * frame_length as usize / 1000,
+        }
+    }
+}
+
+/// VAD 环节逐帧的检测结果，识别器按送去识别的采样数依次取出
+///
+/// 管线一次处理的音频可能包含多个 chunk，结果按采样位置记录，
+/// 识别器处理某个 chunk 时只会取到在该 chunk 内结束的帧，与逐 chunk 检测的效果相同。
+#[derive(Clone, Default)]
+pub struct VoiceActivity {
+    inner: Arc<Mutex<VoiceActivityInner>>,
+}
+
+#[derive(Default)]
+struct VoiceActivityInner {
+    /// 每帧结束的采样位置和是否为人声
+    frames: VecDeque<(u64, bool)>,
+    /// 识别器已取走的采样数
+    consumed: u64,
+}
+
+impl VoiceActivity {
+    fn push(&self, end: u64, is_voice: bool) {
+        self.inner.lock().unwrap().frames.push_back((end, is_voice));
+    }
+
+    /// 识别器取走 `samples` 个采样，依次回调其中已结束的帧是否为人声
+    pub fn advance(&self, samples: usize, mut on_frame: impl FnMut(bool)) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consumed += samples as u64;
+        while let Some(&(end, is_voice)) = inner.frames.front()
+            && end <= inner.consumed
+        {
+            inner.frames.pop_front();
+            on_frame(is_voice);
+        }
+    }
+
+    /// 丢弃所有检测结果，采样位置从 0 开始
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.frames.clear();
+        inner.consumed = 0;
+    }
+}
+
+/// 管线中的 VAD 环节，音频原样输出，检测结果写入 `VoiceActivity`
+///
+/// 必须是管线的最后一个环节，保证检测结果的采样位置与送去识别的音频一致。
+pub struct VadStage {
+    detector: Box<dyn VoiceDetector>,
+    activity: VoiceActivity,
+    /// 未凑满一帧的采样
+    frame: Vec<i16>,
+    /// 已输入的采样数
+    position: u64,
+}
+
+impl VadStage {
+    pub fn new(detector: Box<dyn VoiceDetector>, activity: VoiceActivity) -> Self {
+        let frame = Vec::with_capacity(detector.frame_samples());
+        Self {
+            detector,
+            activity,
+            frame,
+            position: 0,
+        }
+    }
+}
+
+impl AudioStage for VadStage {
+    fn name(&self) -> &'static str {
+        "vad"
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        output.extend_from_slice(input);
+        let frame_samples = self.detector.frame_samples();
+        for &sample in input {
+            self.frame.push(sample.clamp(-32768.0, 32767.0) as i16);
+            self.position += 1;
+            if self.frame.len() == frame_samples {
+                let is_voice = self.detector.is_voice(&self.frame)?;
+                self.activity.push(self.position, is_voice);
+                self.frame.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// 最后不足一帧的音频无法检测，直接丢弃
+    fn flush(&mut self, _output: &mut Vec<f32>) -> Result<()> {
+        self.reset();
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.frame.clear();
+        self.position = 0;
+        self.activity.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 帧内有非零采样即为人声
+    struct NonZero;
+
+    impl VoiceDetector for NonZero {
+        fn frame_samples(&self) -> usize {
+            320
+        }
+
+        fn is_voice(&mut self, frame: &[i16]) -> Result<bool> {
+            Ok(frame.iter().any(|&s| s != 0))
+        }
+    }
+
+    #[test]
+    fn vad_stage_reports_frames_by_position() {
+        let activity = VoiceActivity::default();
+        let mut stage = VadStage::new(Box::new(NonZero), activity.clone());
+
+        // 一次处理两个 chunk 加半帧，第 11 帧 (3200..3520) 跨越两个 chunk
+        let mut input = vec![0.0; 3360];
+        input[3300] = 1000.0;
+        let mut output = Vec::new();
+        stage.process(&input, &mut output).unwrap();
+        assert_eq!(output, input);
+
+        let mut frames = Vec::new();
+        activity.advance(3200, |is_voice| frames.push(is_voice));
+        assert_eq!(frames, vec![false; 10]);
+
+        // 跨越的帧要凑满后才有结果
+        frames.clear();
+        activity.advance(3200, |is_voice| frames.push(is_voice));
+        assert!(frames.is_empty());
+        stage.process(&[0.0; 160], &mut output).unwrap();
+        activity.advance(0, |is_voice| frames.push(is_voice));
+        assert_eq!(frames, [true]);
+    }
+}
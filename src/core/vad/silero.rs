@@ -0,0 +1,56 @@
+use anyhow::{Context, Result};
+use ort::session::Session;
+use ort::value::Tensor;
+
+use super::{SILERO_FRAME_SAMPLES as FRAME_SAMPLES, VoiceDetector};
+/// 模型循环状态的形状
+const STATE_SHAPE: [usize; 3] = [2, 1, 128];
+
+/// Silero VAD 神经网络模型 (ONNX)
+///
+/// 模型下载: https://github.com/snakers4/silero-vad
+pub struct SileroVad {
+    session: Session,
+    state: Vec<f32>,
+    threshold: f32,
+}
+
+impl SileroVad {
+    /// `threshold` 为判断为人声的最低概率
+    pub fn new(model_path: &str, threshold: f32) -> Result<Self> {
+        let session = Session::builder()
+            .and_then(|builder| builder.commit_from_file(model_path))
+            .with_context(|| format!("Failed to load Silero VAD model from {}", model_path))?;
+
+        Ok(Self {
+            session,
+            state: vec![0.0; STATE_SHAPE.iter().product()],
+            threshold,
+        })
+    }
+}
+
+impl VoiceDetector for SileroVad {
+    fn frame_samples(&self) -> usize {
+        FRAME_SAMPLES
+    }
+
+    fn is_voice(&mut self, frame: &[i16]) -> Result<bool> {
+        let input: Vec<f32> = frame.iter().map(|&s| s as f32 / 32768.0).collect();
+        let input = Tensor::from_array(([1, FRAME_SAMPLES], input))?;
+        let state = Tensor::from_array((STATE_SHAPE, self.state.clone()))?;
+        let sr = Tensor::from_array(([1], vec![16000i64]))?;
+
+        let outputs = self
+            .session
+            .run(ort::inputs!["input" => input, "state" => state, "sr" => sr])
+            .context("Failed to run Silero VAD model")?;
+
+        let (_, probability) = outputs["output"].try_extract_tensor::<f32>()?;
+        let is_voice = probability[0] > self.threshold;
+        let (_, state) = outputs["stateN"].try_extract_tensor::<f32>()?;
+        self.state.copy_from_slice(state);
+
+        Ok(is_voice)
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use webrtc_vad::{SampleRate, Vad, VadMode};
+
+use super::VoiceDetector;
+
+/// WebRTC VAD 的激进程度，越激进越不容易把噪音判断为语音
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VadAggressiveness {
+    #[serde(rename = "quality")]
+    Quality,
+    #[serde(rename = "low_bitrate")]
+    LowBitrate,
+    #[serde(rename = "aggressive")]
+    #[default]
+    Aggressive,
+    #[serde(rename = "very_aggressive")]
+    VeryAggressive,
+}
+
+impl From<VadAggressiveness> for VadMode {
+    fn from(value: VadAggressiveness) -> Self {
+        match value {
+            VadAggressiveness::Quality => VadMode::Quality,
+            VadAggressiveness::LowBitrate => VadMode::LowBitrate,
+            VadAggressiveness::Aggressive => VadMode::Aggressive,
+            VadAggressiveness::VeryAggressive => VadMode::VeryAggressive,
+        }
+    }
+}
+
+pub struct WebRtcVad {
+    vad: Vad,
+    frame_samples: usize,
+}
+
+// SAFETY: libfvad 的实例只是一块普通的堆内存，不依赖创建它的线程；
+// `WebRtcVad` 独占该实例，`is_voice` 需要 `&mut self`，不会被多个线程同时访问。
+unsafe impl Send for WebRtcVad {}
+
+impl WebRtcVad {
+    /// `frame_length` 为每帧时长 (ms)，只能是 10、20 或 30
+    pub fn new(mode: VadAggressiveness, frame_length: u64) -> Self {
+        Self {
+            vad: Vad::new_with_rate_and_mode(SampleRate::Rate16kHz, mode.into()),
+            frame_samples: 16000 * frame_length as usize / 1000,
+        }
+    }
+}
+
+impl VoiceDetector for WebRtcVad {
+    fn frame_samples(&self) -> usize {
+        self.frame_samples
+    }
+
+    fn is_voice(&mut self, frame: &[i16]) -> Result<bool> {
+        self.vad
+            .is_voice_segment(frame)
+            .map_err(|e| anyhow!("Failed to detect speech: {:?}", e))
+    }
+}
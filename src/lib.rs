@@ -5,9 +5,7 @@ pub mod utils;
 pub use config::Config;
 
 use anyhow::{Result, anyhow};
-use cpal::traits::{DeviceTrait, HostTrait};
 use log::{info, warn};
-use rand::seq::IndexedRandom;
 use std::collections::HashMap;
 use std::sync::{
     Arc, Mutex,
@@ -17,12 +15,13 @@ use std::thread;
 
 use crate::core::audio::*;
 use crate::core::command::*;
+use crate::core::device::*;
+use crate::core::event::*;
+use crate::core::input::InputStats;
 use crate::core::keypress::*;
 use crate::core::matcher::*;
+use crate::core::recorder::*;
 use crate::core::speaker::*;
-use crate::utils::*;
-
-static AUDIO_DIR: &str = "audio";
 
 /// 复用句柄，stop() 后由调用方持有，传给 restart() 使用。
 /// 包含不能被中断的 rdev listener 线程和 KeyPresser，避免重复 spawn。
@@ -52,10 +51,10 @@ impl EngineHandle {
 
 pub struct HellcallEngine {
     // 字段 drop 顺序 = 声明顺序：
-    // 1. _processor 先 drop → 音频线程 join → on_result 闭包 drop → Arc<Command> drop → 命令闭包 drop
+    // 1. processor 先 drop → 音频线程 join → on_result 闭包 drop → Arc<Command> drop → 命令闭包 drop
     // 2. _speaker 后 drop → 命令闭包里的 speaker_ref 都已 drop，此时引用计数归零 → Speaker 线程退出
     // 3. cancel_flag 最后 drop
-    _processor: AudioBufferProcessor,
+    processor: AudioBufferProcessor,
     _speaker: Arc<Speaker>,
     cancel_flag: Arc<AtomicBool>,
     // 以下两个字段由 stop(self) 转移给 EngineHandle，不在这里 drop。
@@ -74,30 +73,26 @@ impl HellcallEngine {
     }
 
     fn start_inner(
-        config: Config,
+        mut config: Config,
         model_path: &str,
         input_device_name: Option<String>,
         audio_dir: Option<String>,
         existing: Option<(Arc<KeyPresser>, thread::JoinHandle<()>)>,
     ) -> Result<Self> {
-        // 选择输入设备
-        let input_device = if let Some(name) = input_device_name.filter(|n| !n.is_empty()) {
-            name
-        } else {
-            let host = cpal::default_host();
-            let default_device = host
-                .default_input_device()
-                .ok_or_else(|| anyhow!("No default input device found"))?;
-            default_device.name()?
+        // 选择输入设备：参数 > 配置 > 自动选择
+        let host = get_host(config.device.host.as_deref())?;
+        let input_device = match input_device_name
+            .filter(|n| !n.is_empty())
+            .or_else(|| config.device.input.clone().filter(|n| !n.is_empty()))
+        {
+            Some(device) => device,
+            None => select_input_device(&host, &config.device.exclude_inputs)?,
         };
         info!("input_device_name: {}", input_device);
 
-        // 选择音频目录
-        let audio_dir = if let Some(dir) = audio_dir.filter(|d| !d.is_empty()) {
-            dir
-        } else {
-            AUDIO_DIR.to_string()
-        };
+        // 选择音频目录，移除无法播放的音效
+        let audio_dir = resolve_audio_dir(audio_dir.as_deref())?;
+        config.remove_invalid_audio_files(&audio_dir);
 
         // 初始化 KeyPresser 和 listener（首次创建或复用）
         let key_presser_config = config.key_presser.clone();
@@ -105,7 +100,7 @@ impl HellcallEngine {
             .commands
             .iter()
             .filter(|cmd| cmd.shortcut.is_some())
-            .map(|cmd| (cmd.shortcut.clone().unwrap(), cmd.keys.clone()))
+            .map(|cmd| (cmd.shortcut.clone().unwrap(), cmd.action()))
             .collect::<HashMap<_, _>>();
 
         let (key_presser, listener_handle) = if let Some((kp, lh)) = existing {
@@ -127,7 +122,28 @@ impl HellcallEngine {
         };
 
         // 初始化 Speaker（每次都新建，stop 时会随 Engine 一起 drop）
-        let speaker = Arc::new(Speaker::new()?);
+        let speaker = Arc::new(Speaker::with_options(
+            config.speaker_options(),
+            key_presser.events(),
+        )?);
+
+        // 待执行战备过期提示音
+        // 使用 Weak 引用：KeyPresser 跨重启复用，不能延长本次 Speaker 的生命周期
+        {
+            let expired_audio_files = config.key_presser.expired_audio_files.clone();
+            let speaker_ref = Arc::downgrade(&speaker);
+            let audio_dir = audio_dir.clone();
+            key_presser.on_pending_expired(move |_| {
+                if let Some(speaker) = speaker_ref.upgrade() {
+                    play_random_audio(
+                        &speaker,
+                        &audio_dir,
+                        &expired_audio_files,
+                        &PlaybackOptions::default(),
+                    );
+                }
+            });
+        }
 
         // 构建命令表
         let command_map: HashMap<String, Box<dyn Fn() + Send + Sync>> = config
@@ -136,26 +152,21 @@ impl HellcallEngine {
             .map(|cmd| -> Result<(String, Box<dyn Fn() + Send + Sync>)> {
                 let key_presser_ref = Arc::clone(&key_presser);
                 let speaker_ref = Arc::clone(&speaker);
-                let keys = cmd.keys.clone();
+                let action = cmd.action();
                 let audio_files = cmd.audio_files.clone();
+                let playback = cmd.playback();
                 let audio_dir = audio_dir.clone();
 
                 if cmd.command.is_empty() {
                     return Err(anyhow!("command must not be empty"));
                 };
-                KeyPresser::has_validity(keys.as_slice())?;
+                KeyPresser::check_action(&action)?;
 
                 Ok((
                     cmd.command.clone(),
                     Box::new(move || {
-                        key_presser_ref.push(keys.as_slice());
-                        if let Some(audio_path) = audio_files.choose(&mut rand::rng()) {
-                            let audio_path = std::env::current_dir()
-                                .unwrap()
-                                .join(&audio_dir)
-                                .join(audio_path);
-                            let _ = speaker_ref.play_wav(audio_path.to_str().unwrap());
-                        }
+                        key_presser_ref.execute(&action);
+                        play_random_audio(&speaker_ref, &audio_dir, &audio_files, &playback);
                     }) as Box<dyn Fn() + Send + Sync>,
                 ))
             })
@@ -167,36 +178,55 @@ impl HellcallEngine {
         let trigger = config.trigger.clone();
 
         let mut audio_recognizer_config: AudioRecognizerConfig = config.recognizer.clone().into();
-        let mut grammar: Vec<String> = config
-            .commands
-            .iter()
-            .map(|cmd| {
-                let grammar = cmd.grammar.clone();
-                if let Some(grammar) = grammar {
-                    if !grammar.is_empty() {
-                        return grammar;
-                    }
-                }
-                cmd.command.clone().add_between_chars(" ")
-            })
-            .collect();
-
-        if let Some(hit_word_grammar) = trigger.hit_word_grammar.clone().filter(|g| !g.is_empty()) {
-            grammar.push(hit_word_grammar);
-        } else if !&trigger.hit_word.is_empty() {
-            grammar.push(trigger.hit_word.clone().unwrap().add_between_chars(" "));
-        }
-
-        audio_recognizer_config.set_grammar(grammar);
-        let recognizer = AudioRecognizer::new(model_path, audio_recognizer_config)?;
-        let mut processor =
-            AudioBufferProcessor::new_with_input_device_name(recognizer, input_device)?;
+        audio_recognizer_config.set_grammar(config.grammar());
+        audio_recognizer_config.set_recording(config.recorder.enable);
+        let recorder = if config.recorder.enable {
+            Some(UtteranceRecorder::new(
+                &config.recorder.dir,
+                config.recorder.max_recordings,
+            )?)
+        } else {
+            None
+        };
+        let mut recognizer = AudioRecognizer::new(model_path, audio_recognizer_config)?;
+        recognizer.set_events(key_presser.events());
+        let mut processor = AudioBufferProcessor::new_with_host(
+            recognizer,
+            config.device.host.clone(),
+            Some(input_device),
+        )?;
 
         // listen push-to-talk key
         if let Some(ptt_input) = config.key_map.get(&LocalKey::PTT).cloned() {
             let speech_ctrl = processor.get_speech_controller();
-            let _ = key_presser.listen_key(ptt_input, move |speaking| {
-                speech_ctrl.set_is_speaking(speaking);
+            let _ = key_presser.listen_key(ptt_input, move |pressed| {
+                speech_ctrl.handle_talk_key(pressed);
+            });
+        }
+
+        // TOGGLE 键暂停/恢复语音识别，KeyPresser 跨重启复用，需同步当前状态
+        {
+            let speech_ctrl = processor.get_speech_controller();
+            speech_ctrl.set_paused(!key_presser.is_enabled());
+            let speaker_ref = Arc::downgrade(&speaker);
+            let audio_dir = audio_dir.clone();
+            let enabled_audio_files = config.key_presser.enabled_audio_files.clone();
+            let disabled_audio_files = config.key_presser.disabled_audio_files.clone();
+            key_presser.on_toggle(move |enabled| {
+                speech_ctrl.set_paused(!enabled);
+                if let Some(speaker) = speaker_ref.upgrade() {
+                    let audio_files = if enabled {
+                        &enabled_audio_files
+                    } else {
+                        &disabled_audio_files
+                    };
+                    play_random_audio(
+                        &speaker,
+                        &audio_dir,
+                        audio_files,
+                        &PlaybackOptions::default(),
+                    );
+                }
             });
         }
 
@@ -210,44 +240,26 @@ impl HellcallEngine {
                 return;
             }
 
-            let speech = result.text.trim();
-            if speech.is_empty() {
-                return;
+            let outcome = match_result(
+                &result,
+                trigger.hit_word.as_deref(),
+                &mut matcher_ref.lock().unwrap(),
+            );
+            if let Some(command) = outcome.command() {
+                command_ref.execute(command);
             }
 
-            let speech = speech.replace(" ", "");
-            let hit_word = trigger.hit_word.clone();
-            let command_to_match = if hit_word.is_empty() {
-                info!("speech: {}", speech);
-                speech
-            } else {
-                let hit_word = hit_word.unwrap();
-                if let Some(pos) = speech.rfind(hit_word.as_str()) {
-                    let command_str = &speech[pos + hit_word.len()..];
-                    info!("speech: {} {}", hit_word, command_str);
-                    command_str.to_string()
-                } else {
-                    warn!("miss required word '{}': {}", hit_word, speech);
-                    return;
-                }
-            };
-
-            if let Some(command) = matcher_ref
-                .lock()
-                .unwrap()
-                .match_str(command_to_match.as_str())
+            if let (Some(recorder), Some(recording)) = (&recorder, &result.recording)
+                && let Err(e) = recorder.save(Arc::clone(recording), result.text, outcome)
             {
-                info!("hit command: {}", command);
-                command_ref.execute(command.as_str());
-            } else {
-                warn!("no matching command found: {}", command_to_match);
+                warn!("Failed to save recording: {}", e);
             }
         });
 
         processor.start(on_result)?;
 
         Ok(HellcallEngine {
-            _processor: processor,
+            processor,
             _speaker: speaker,
             cancel_flag,
             _key_presser: key_presser,
@@ -255,10 +267,27 @@ impl HellcallEngine {
         })
     }
 
+    /// 设置事件监听器。
+    ///
+    /// 监听器挂在 KeyPresser 上，stop() 后经 EngineHandle::restart() 重启依然有效。
+    pub fn set_event_listener<F>(&self, listener: F)
+    where
+        F: Fn(HellcallEvent) + Send + Sync + 'static,
+    {
+        self._key_presser.events().set_listener(listener);
+    }
+
+    /// 输入采样的统计，可定期读取以展示丢弃和削波的采样数。
+    ///
+    /// 每次启动都会重新计数，restart() 后需要重新获取。
+    pub fn input_stats(&self) -> Arc<InputStats> {
+        self.processor.input_stats()
+    }
+
     /// 停止引擎，消耗 self。
     ///
     /// drop 顺序（由字段声明顺序保证）：
-    ///   1. `_processor` drop → 音频线程 join → on_result 闭包 drop → Arc<Command> drop
+    ///   1. `processor` drop → 音频线程 join → on_result 闭包 drop → Arc<Command> drop
     ///      → 命令闭包 drop → 闭包内的 speaker_ref Arc 和 key_presser_ref Arc 全部 release
     ///   2. `_speaker` drop → 上一步所有 speaker_ref 已 release，此时引用计数归零
     ///      → Speaker::drop → tx drop → Speaker 线程退出
@@ -273,7 +302,7 @@ impl HellcallEngine {
             key_presser: self._key_presser,
             listener_handle: self._listener_handle.unwrap(),
         };
-        // self 中剩余的 _processor、_speaker、cancel_flag 此处 drop（按声明顺序）
+        // self 中剩余的 processor、_speaker、cancel_flag 此处 drop（按声明顺序）
         handle
     }
 }
@@ -1,10 +1,8 @@
 // #![allow(unused)]
 
 use anyhow::{Result, anyhow};
-use cpal::traits::{DeviceTrait, HostTrait};
 use inquire::Select;
 use log::{info, warn};
-use rand::seq::IndexedRandom;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::{env, fs};
@@ -13,17 +11,16 @@ use vosk::LogLevel;
 use config::*;
 use core::audio::*;
 use core::command::*;
+use core::device::*;
 use core::keypress::*;
 use core::matcher::*;
+use core::recorder::*;
 use core::speaker::*;
-use utils::*;
 
 pub mod config;
 pub mod core;
 mod utils;
 
-static AUDIO_DIR: &str = "audio";
-
 fn main() -> Result<()> {
     // print banner
     print_banner();
@@ -44,26 +41,50 @@ fn main() -> Result<()> {
 
     // load config
     let content = fs::read_to_string(&config_path)?;
-    let config: Config = toml::from_str(&content)?;
+    let mut config: Config = toml::from_str(&content)?;
 
     // choose input device
-    let input_device_name = get_input_device_name()?;
+    let input_device_name = get_input_device_name(&config_path, &config.device)?;
     info!("input_device_name: {}", input_device_name);
 
+    // skip audio files that can't be played, they are logged by validate_audio_files
+    let audio_dir = resolve_audio_dir(None)?;
+    config.remove_invalid_audio_files(&audio_dir);
+
     // init
-    let key_presser_config = config.key_presser;
+    let expired_audio_files = config.key_presser.expired_audio_files.clone();
+    let enabled_audio_files = config.key_presser.enabled_audio_files.clone();
+    let disabled_audio_files = config.key_presser.disabled_audio_files.clone();
+    let key_presser_config = config.key_presser.clone();
     let shortcut = config
         .commands
         .iter()
         .filter(|cmd| cmd.shortcut.is_some())
-        .map(|cmd| (cmd.shortcut.clone().unwrap(), cmd.keys.clone()))
+        .map(|cmd| (cmd.shortcut.clone().unwrap(), cmd.action()))
         .collect::<HashMap<_, _>>();
     let key_presser = Arc::new(KeyPresser::new(
         key_presser_config,
         config.key_map.clone(),
         shortcut,
     )?);
-    let speaker = Arc::new(Speaker::new()?);
+    let speaker = Arc::new(Speaker::with_options(
+        config.speaker_options(),
+        key_presser.events(),
+    )?);
+
+    // play audio when a pending stratagem expires
+    {
+        let speaker_ref = Arc::clone(&speaker);
+        let audio_dir = audio_dir.clone();
+        key_presser.on_pending_expired(move |_| {
+            play_random_audio(
+                &speaker_ref,
+                &audio_dir,
+                &expired_audio_files,
+                &PlaybackOptions::default(),
+            );
+        });
+    }
 
     let command_map: HashMap<String, Box<dyn Fn() + Send + Sync>> = config
         .commands
@@ -71,26 +92,22 @@ fn main() -> Result<()> {
         .map(|cmd| -> Result<(String, Box<dyn Fn() + Send + Sync>)> {
             let key_presser_ref = Arc::clone(&key_presser);
             let speaker_ref = Arc::clone(&speaker);
-            let keys = cmd.keys.clone();
+            let action = cmd.action();
             let audio_files = cmd.audio_files.clone();
+            let playback = cmd.playback();
+            let audio_dir = audio_dir.clone();
 
             // check
             if cmd.command.is_empty() {
                 return Err(anyhow!("command must not be empty"));
             };
-            KeyPresser::has_validity(keys.as_slice())?;
+            KeyPresser::check_action(&action)?;
 
             Ok((
                 cmd.command.clone(),
                 Box::new(move || {
-                    key_presser_ref.push(keys.as_slice());
-                    if let Some(audio_path) = audio_files.choose(&mut rand::rng()) {
-                        let audio_path = std::env::current_dir()
-                            .unwrap()
-                            .join(AUDIO_DIR)
-                            .join(audio_path);
-                        speaker_ref.play_wav(audio_path.to_str().unwrap()).unwrap();
-                    }
+                    key_presser_ref.execute(&action);
+                    play_random_audio(&speaker_ref, &audio_dir, &audio_files, &playback);
                 }) as Box<dyn Fn() + Send + Sync>,
             ))
         })
@@ -102,77 +119,71 @@ fn main() -> Result<()> {
 
     let trigger = config.trigger.clone();
 
-    let mut audio_recognizer_config: AudioRecognizerConfig = config.recognizer.into();
-    let mut grammar: Vec<String> = config
-        .commands
-        .iter()
-        .map(|cmd| {
-            let grammar = cmd.grammar.clone();
-            if let Some(grammar) = grammar {
-                if !grammar.is_empty() {
-                    return grammar;
-                }
-            }
-            let command = cmd.command.clone();
-            command.add_between_chars(" ")
-        })
-        .collect();
-
-    if let Some(hit_word_grammar) = trigger.hit_word_grammar.clone().filter(|g| !g.is_empty()) {
-        grammar.push(hit_word_grammar);
-    } else if !&trigger.hit_word.is_empty() {
-        grammar.push(trigger.hit_word.clone().unwrap().add_between_chars(" "));
-    }
-
-    audio_recognizer_config.set_grammar(grammar);
+    let mut audio_recognizer_config: AudioRecognizerConfig = config.recognizer.clone().into();
+    audio_recognizer_config.set_grammar(config.grammar());
+    audio_recognizer_config.set_recording(config.recorder.enable);
+    let recorder = if config.recorder.enable {
+        Some(UtteranceRecorder::new(
+            &config.recorder.dir,
+            config.recorder.max_recordings,
+        )?)
+    } else {
+        None
+    };
     let recognizer = AudioRecognizer::new(model_path.as_str(), audio_recognizer_config)?;
-    let mut processor =
-        AudioBufferProcessor::new_with_input_device_name(recognizer, input_device_name)?;
+    let mut processor = AudioBufferProcessor::new_with_host(
+        recognizer,
+        config.device.host.clone(),
+        Some(input_device_name),
+    )?;
 
     // listen push-to-talk key
     if let Some(ptt_input) = config.key_map.get(&LocalKey::PTT).cloned() {
         let speech_ctrl = processor.get_speech_controller();
-        key_presser.listen_key(ptt_input, move |speaking| {
-            speech_ctrl.set_is_speaking(speaking);
+        key_presser.listen_key(ptt_input, move |pressed| {
+            speech_ctrl.handle_talk_key(pressed);
         })?;
     }
 
+    // pause recognition with toggle key
+    {
+        let speech_ctrl = processor.get_speech_controller();
+        let speaker_ref = Arc::clone(&speaker);
+        let audio_dir = audio_dir.clone();
+        key_presser.on_toggle(move |enabled| {
+            speech_ctrl.set_paused(!enabled);
+            let audio_files = if enabled {
+                &enabled_audio_files
+            } else {
+                &disabled_audio_files
+            };
+            play_random_audio(
+                &speaker_ref,
+                &audio_dir,
+                audio_files,
+                &PlaybackOptions::default(),
+            );
+        });
+    }
+
     let command_ref = Arc::clone(&command);
     let matcher_ref = Arc::clone(&matcher);
     let on_result = Box::new(move |result: RecognitionResult| {
-        let speech = result.text.trim();
-        if speech.is_empty() {
-            return;
+        // match command
+        let outcome = match_result(
+            &result,
+            trigger.hit_word.as_deref(),
+            &mut matcher_ref.lock().unwrap(),
+        );
+        if let Some(command) = outcome.command() {
+            command_ref.execute(command);
         }
 
-        // process speech
-        let speech = speech.replace(" ", "");
-        let hit_word = trigger.hit_word.clone();
-        let command_to_match = if hit_word.is_empty() {
-            info!("speech: {}", speech);
-            speech
-        } else {
-            let hit_word = hit_word.unwrap();
-            if let Some(pos) = speech.rfind(hit_word.as_str()) {
-                let command_str = &speech[pos + hit_word.len()..];
-                info!("speech: {} {}", hit_word, command_str);
-                command_str.to_string()
-            } else {
-                warn!("miss required word '{}': {}", hit_word, speech);
-                return;
-            }
-        };
-
-        // match command
-        if let Some(command) = matcher_ref
-            .lock()
-            .unwrap()
-            .match_str(command_to_match.as_str())
+        // save recording
+        if let (Some(recorder), Some(recording)) = (&recorder, &result.recording)
+            && let Err(e) = recorder.save(Arc::clone(recording), result.text, outcome)
         {
-            info!("hit command: {}", command);
-            command_ref.execute(command.as_str());
-        } else {
-            warn!("no matching command found: {}", command_to_match);
+            warn!("Failed to save recording: {}", e);
         }
     });
 
@@ -204,21 +215,103 @@ HellCall v{} - Helldivers 2 语音指令工具
     );
 }
 
-fn get_input_device_name() -> Result<String> {
-    let host = cpal::default_host();
+fn get_input_device_name(config_path: &str, device: &DeviceConfig) -> Result<String> {
+    if let Some(input) = device.input.clone().filter(|x| !x.is_empty()) {
+        return Ok(input);
+    }
 
-    if let Some(default_device) = host.default_input_device() {
-        let device_name = default_device.name()?;
-        if device_name.find("VB-Audio Virtual Cable").is_none() {
-            return Ok(device_name.to_string());
-        }
+    let host = get_host(device.host.as_deref())?;
+    let mut devices = list_input_devices(&host, &device.exclude_inputs)?;
+    // 同名设备的 ID 不稳定，不能保存到配置
+    devices.retain(|x| !x.ambiguous);
+    if let Some(default_device) = devices.iter().find(|x| x.is_default) {
+        return Ok(default_device.id.clone());
+    }
+    if devices.is_empty() {
+        return Err(anyhow!("No available input device found"));
+    }
+
+    let device_names = devices.iter().map(|x| x.name.clone()).collect::<Vec<_>>();
+    let selected = Select::new("请选择麦克风设备", device_names).raw_prompt()?;
+    let device_id = devices[selected.index].id.clone();
+
+    // remember the choice so the prompt is not needed next launch
+    match save_input_device(config_path, &device_id) {
+        Ok(()) => info!("input device saved to {}", config_path),
+        Err(e) => warn!("failed to save input device to {}: {}", config_path, e),
+    }
+    Ok(device_id)
+}
+
+/// 将选择的麦克风写入配置文件的 [device] 段
+///
+/// 只修改 `[device].input`，文件中的注释、顺序和格式保持不变。
+fn save_input_device(config_path: &str, device_id: &str) -> Result<()> {
+    let content = fs::read_to_string(config_path)?;
+    let mut config: toml_edit::DocumentMut = content.parse()?;
+    let device = config
+        .entry("device")
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .ok_or_else(|| anyhow!("[device] is not a table"))?;
+    device.insert("input", toml_edit::value(device_id));
+
+    fs::write(config_path, config.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在临时目录中写入配置文件，返回其路径
+    fn write_config(name: &str, content: &str) -> String {
+        let dir = env::temp_dir().join(format!("hellcall-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn save_input_device_keeps_comments() {
+        let content = r#"# 顶部注释
+[device]
+# 麦克风
+# input = "wasapi:old"
+exclude_inputs = ["VB-Audio"] # 虚拟声卡
+
+[speaker]
+# 音量
+volume = 0.8
+"#;
+        let path = write_config("save-device", content);
+        save_input_device(&path, "wasapi:Mic").unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            saved,
+            content.replace(
+                "exclude_inputs = [\"VB-Audio\"] # 虚拟声卡\n",
+                "exclude_inputs = [\"VB-Audio\"] # 虚拟声卡\ninput = \"wasapi:Mic\"\n",
+            )
+        );
     }
 
-    let devices = host.input_devices()?;
-    let device_names = devices
-        .into_iter()
-        .map(|x| x.name().unwrap())
-        .collect::<Vec<_>>();
-    let device_name = Select::new("请选择麦克风设备", device_names).prompt()?;
-    Ok(device_name)
+    #[test]
+    fn save_input_device_replaces_value_and_adds_table() {
+        let path = write_config("replace-device", "[device]\ninput = \"old\" # 旧设备\n");
+        save_input_device(&path, "new").unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("input = \"new\""), "{}", saved);
+        assert!(!saved.contains("old"), "{}", saved);
+
+        fs::write(&path, "# 音效\n[speaker]\nvolume = 0.8\n").unwrap();
+        save_input_device(&path, "new").unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(saved.starts_with("# 音效\n[speaker]\n"), "{}", saved);
+        assert!(saved.contains("[device]\ninput = \"new\""), "{}", saved);
+    }
 }